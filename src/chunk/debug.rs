@@ -1,4 +1,7 @@
-use crate::chunk::{opcode::OpCode, Chunk};
+use crate::{
+    chunk::{opcode::OpCode, Chunk},
+    value::Value,
+};
 use std::ops::Shl;
 
 enum JumpDirection {
@@ -37,14 +40,15 @@ impl Chunk {
             False => self.simple_instruction(False.to_string().as_str(), offset),
             // global variable pop
             Pop => self.simple_instruction(Pop.to_string().as_str(), offset),
+            Dup => self.byte_instruction(Dup.to_string().as_str(), offset),
             End => self.simple_instruction(End.to_string().as_str(), offset),
             // local variable
             GetLocal => self.byte_instruction(GetLocal.to_string().as_str(), offset),
             SetLocal => self.byte_instruction(SetLocal.to_string().as_str(), offset),
             // global variable
-            GetGlobal => self.constant_instruction(GetGlobal.to_string().as_str(), offset),
-            SetGlobal => self.constant_instruction(SetGlobal.to_string().as_str(), offset),
-            DefineGlobal => self.constant_instruction(DefineGlobal.to_string().as_str(), offset),
+            GetGlobal => self.slot_instruction(GetGlobal.to_string().as_str(), offset),
+            SetGlobal => self.slot_instruction(SetGlobal.to_string().as_str(), offset),
+            DefineGlobal => self.slot_instruction(DefineGlobal.to_string().as_str(), offset),
             // property
             GetProperty => self.constant_instruction(GetProperty.to_string().as_str(), offset),
             SetProperty => self.constant_instruction(SetProperty.to_string().as_str(), offset),
@@ -77,25 +81,183 @@ impl Chunk {
             // struct
             Struct => self.constant_instruction(Struct.to_string().as_str(), offset),
             Method => self.constant_instruction(Method.to_string().as_str(), offset),
+            // closure
+            Closure => self.closure_instruction(Closure.to_string().as_str(), offset),
+            GetUpvalue => self.byte_instruction(GetUpvalue.to_string().as_str(), offset),
+            SetUpvalue => self.byte_instruction(SetUpvalue.to_string().as_str(), offset),
+            CloseUpvalue => self.simple_instruction(CloseUpvalue.to_string().as_str(), offset),
+            // inheritance
+            Inherit => self.simple_instruction(Inherit.to_string().as_str(), offset),
+            GetSuper => self.constant_instruction(GetSuper.to_string().as_str(), offset),
+            SuperInvoke => self.invoke_instruction(SuperInvoke.to_string().as_str(), offset),
+            // list
+            BuildList => self.byte_instruction(BuildList.to_string().as_str(), offset),
+            GetIndex => self.simple_instruction(GetIndex.to_string().as_str(), offset),
+            SetIndex => self.simple_instruction(SetIndex.to_string().as_str(), offset),
+            // map
+            BuildMap => self.byte_instruction(BuildMap.to_string().as_str(), offset),
+            // module
+            Import => self.constant_instruction(Import.to_string().as_str(), offset),
+            // string
+            Stringify => self.simple_instruction(Stringify.to_string().as_str(), offset),
+            // wide constant operands
+            ConstantLong => self.constant_instruction(ConstantLong.to_string().as_str(), offset),
+            GetGlobalLong => self.slot_instruction(GetGlobalLong.to_string().as_str(), offset),
+            SetGlobalLong => self.slot_instruction(SetGlobalLong.to_string().as_str(), offset),
+            DefineGlobalLong => {
+                self.slot_instruction(DefineGlobalLong.to_string().as_str(), offset)
+            }
+            GetPropertyLong => {
+                self.constant_instruction(GetPropertyLong.to_string().as_str(), offset)
+            }
+            SetPropertyLong => {
+                self.constant_instruction(SetPropertyLong.to_string().as_str(), offset)
+            }
+            InvokeLong => self.invoke_instruction(InvokeLong.to_string().as_str(), offset),
+            StructLong => self.constant_instruction(StructLong.to_string().as_str(), offset),
+            MethodLong => self.constant_instruction(MethodLong.to_string().as_str(), offset),
+            ClosureLong => self.closure_instruction(ClosureLong.to_string().as_str(), offset),
+            GetSuperLong => self.constant_instruction(GetSuperLong.to_string().as_str(), offset),
+            SuperInvokeLong => {
+                self.invoke_instruction(SuperInvokeLong.to_string().as_str(), offset)
+            }
+            ImportLong => self.constant_instruction(ImportLong.to_string().as_str(), offset),
+            // exceptions
+            PushHandler => self.handler_instruction(PushHandler.to_string().as_str(), offset),
+            PopHandler => self.simple_instruction(PopHandler.to_string().as_str(), offset),
+            Throw => self.simple_instruction(Throw.to_string().as_str(), offset),
+            EndFinally => self.end_finally_instruction(EndFinally.to_string().as_str(), offset),
+            // iteration
+            Range => self.simple_instruction(Range.to_string().as_str(), offset),
+            RangeInclusive => self.simple_instruction(RangeInclusive.to_string().as_str(), offset),
+            Iter => self.simple_instruction(Iter.to_string().as_str(), offset),
+            ForIter => self.for_iter_instruction(ForIter.to_string().as_str(), offset),
+            // patterns
+            IsInstance => self.simple_instruction(IsInstance.to_string().as_str(), offset),
+            MatchList => self.match_list_instruction(MatchList.to_string().as_str(), offset),
+            MatchError => self.simple_instruction(MatchError.to_string().as_str(), offset),
+            IsVariant => self.is_variant_instruction(IsVariant.to_string().as_str(), offset),
+            GetPayload => self.byte_instruction(GetPayload.to_string().as_str(), offset),
+            // fields
+            Field => self.field_instruction(Field.to_string().as_str(), offset),
+            FieldLong => self.field_instruction(FieldLong.to_string().as_str(), offset),
+            BuildInstance => self.byte_instruction(BuildInstance.to_string().as_str(), offset),
+            StaticMethod => self.constant_instruction(StaticMethod.to_string().as_str(), offset),
+            StaticMethodLong => {
+                self.constant_instruction(StaticMethodLong.to_string().as_str(), offset)
+            }
             _ => panic!("Unknown Opcode"),
         }
     }
 
+    fn end_finally_instruction(&self, name: &str, offset: usize) -> usize {
+        let target = |at: usize| {
+            let jump = (self.bytecodes[at] as usize).shl(8) | (self.bytecodes[at + 1] as usize);
+            offset + 5 + jump
+        };
+        println!(
+            "{name:<16} break -> {}, continue -> {}",
+            target(offset + 1),
+            target(offset + 3)
+        );
+        offset + 5
+    }
+
+    fn for_iter_instruction(&self, name: &str, offset: usize) -> usize {
+        let slot = self.bytecodes[offset + 1];
+        let count = self.bytecodes[offset + 2];
+        let jump =
+            (self.bytecodes[offset + 3] as usize).shl(8) | (self.bytecodes[offset + 4] as usize);
+        println!(
+            "{name:<16} {slot:>4} ({count} vars) -> {}",
+            offset + 5 + jump
+        );
+        offset + 5
+    }
+
+    fn match_list_instruction(&self, name: &str, offset: usize) -> usize {
+        let len = self.bytecodes[offset + 1];
+        let rest = if self.bytecodes[offset + 2] == 1 {
+            " or more"
+        } else {
+            ""
+        };
+        println!("{name:<16} {len:>4}{rest}");
+        offset + 3
+    }
+
+    fn is_variant_instruction(&self, name: &str, offset: usize) -> usize {
+        let count = self.bytecodes[offset + 1];
+        if self.bytecodes[offset + 2] == 1 {
+            println!("{name:<16} {count:>4} fields");
+        } else {
+            println!("{name:<16}  any");
+        }
+        offset + 3
+    }
+
     fn constant_instruction(&self, name: &str, offset: usize) -> usize {
-        let index = self.bytecodes[offset + 1];
+        let (index, offset) = self.operand(offset);
         print!("{name:<16} {index:>4} '");
-        self.constant_pool.print_nth(index as usize);
+        self.constant_pool.print_nth(index);
         println!("'");
-        offset + 2
+        offset
+    }
+
+    fn closure_instruction(&self, name: &str, offset: usize) -> usize {
+        let (index, mut offset) = self.operand(offset);
+        print!("{name:<16} {index:>4} '");
+        self.constant_pool.print_nth(index);
+        println!("'");
+
+        if let Value::Function(function) = self.constant_pool.get(index) {
+            for _ in 0..function.upvalue_count {
+                let is_local = self.bytecodes[offset];
+                let index = self.bytecodes[offset + 1];
+                let kind = if is_local == 1 { "local" } else { "upvalue" };
+                println!("{:04}    |                     {kind} {index}", offset);
+                offset += 2;
+            }
+        }
+        offset
+    }
+
+    fn field_instruction(&self, name: &str, offset: usize) -> usize {
+        let (index, offset) = self.operand(offset);
+        let default = if self.bytecodes[offset] == 1 {
+            " with default"
+        } else {
+            ""
+        };
+        print!("{name:<16} {index:>4} '");
+        self.constant_pool.print_nth(index);
+        println!("'{default}");
+        offset + 1
     }
 
     fn invoke_instruction(&self, name: &str, offset: usize) -> usize {
-        let index = self.bytecodes[offset + 1];
-        let arg_count = self.bytecodes[offset + 2];
+        let (index, offset) = self.operand(offset);
+        let arg_count = self.bytecodes[offset];
         print!("{name:<16} ({arg_count} args) {index:>4} '");
-        self.constant_pool.print_nth(index as usize);
+        self.constant_pool.print_nth(index);
         println!("'");
-        offset + 3
+        offset + 1
+    }
+
+    fn slot_instruction(&self, name: &str, offset: usize) -> usize {
+        let (slot, offset) = self.operand(offset);
+        println!("{name:<16} {slot:>4}");
+        offset
+    }
+
+    // Returns the operand and the offset just past it
+    fn operand(&self, offset: usize) -> (usize, usize) {
+        let instruction: OpCode = self.bytecodes[offset].into();
+        if instruction.is_long() {
+            (self.read_long_operand(offset + 1), offset + 4)
+        } else {
+            (self.bytecodes[offset + 1] as usize, offset + 2)
+        }
     }
 
     fn simple_instruction(&self, name: &str, offset: usize) -> usize {
@@ -120,4 +282,18 @@ impl Chunk {
         println!("{name:<16} {offset:>4} -> {jump}");
         offset + 3
     }
+
+    fn handler_instruction(&self, name: &str, offset: usize) -> usize {
+        let catch =
+            (self.bytecodes[offset + 1] as usize).shl(8) | (self.bytecodes[offset + 2] as usize);
+        let finally =
+            (self.bytecodes[offset + 3] as usize).shl(8) | (self.bytecodes[offset + 4] as usize);
+        let slots = self.bytecodes[offset + 5];
+        println!(
+            "{name:<16} {offset:>4} -> {} finally {} keeps {slots}",
+            offset + 3 + catch,
+            offset + 5 + finally
+        );
+        offset + 6
+    }
 }
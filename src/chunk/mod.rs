@@ -1,12 +1,42 @@
 #[cfg(feature = "debug_mode")]
 pub mod debug;
 pub mod opcode;
-use crate::value::{ConstantPool, Value};
+use crate::{
+    chunk::opcode::OpCode,
+    diagnostic::Span,
+    value::{ConstantPool, Value},
+};
+use std::{collections::HashMap, rc::Rc};
+
+// Wide instructions address constants and globals with a 24-bit operand
+pub const MAX_CONSTANTS: usize = 1 << 24;
+pub const MAX_GLOBALS: usize = 1 << 24;
+
+// Literal constants that can share a pool slot
+#[derive(PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Number(u64),
+    String(Rc<str>),
+}
+
+impl ConstantKey {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => Some(ConstantKey::Number(n.to_bits())),
+            Value::String(s) => Some(ConstantKey::String(s.clone())),
+            _ => None,
+        }
+    }
+}
 
 pub struct Chunk {
     pub bytecodes: Vec<u8>,
     pub line_numbers: Vec<usize>,
+    pub spans: Vec<Span>,
+    // The text the spans index into
+    pub source: Option<Rc<str>>,
     pub constant_pool: ConstantPool,
+    constant_indices: HashMap<ConstantKey, usize>,
 }
 
 impl Chunk {
@@ -14,16 +44,93 @@ impl Chunk {
         Chunk {
             bytecodes: Vec::new(),
             line_numbers: Vec::new(),
+            spans: Vec::new(),
+            source: None,
             constant_pool: ConstantPool::new(),
+            constant_indices: HashMap::new(),
         }
     }
 
-    pub fn push_bytecode<T: Into<u8>>(&mut self, byte: T, line_number: usize) {
+    pub fn push_bytecode<T: Into<u8>>(&mut self, byte: T, line_number: usize, span: Span) {
         self.bytecodes.push(byte.into());
         self.line_numbers.push(line_number);
+        self.spans.push(span);
+    }
+
+    pub fn push_constant(&mut self, value: Value) -> usize {
+        let Some(key) = ConstantKey::from_value(&value) else {
+            return self.constant_pool.push(value);
+        };
+        if let Some(&index) = self.constant_indices.get(&key) {
+            return index;
+        }
+        let index = self.constant_pool.push(value);
+        self.constant_indices.insert(key, index);
+        index
     }
 
-    pub fn push_constant(&mut self, value: Value) -> Option<u8> {
-        self.constant_pool.push(value).try_into().ok()
+    pub fn read_long_operand(&self, offset: usize) -> usize {
+        let bytes = &self.bytecodes[offset..offset + 3];
+        ((bytes[0] as usize) << 16) | ((bytes[1] as usize) << 8) | bytes[2] as usize
+    }
+
+    // The constant index operand of the instruction at offset
+    fn constant_operand(&self, offset: usize) -> usize {
+        if OpCode::from(self.bytecodes[offset]).is_long() {
+            self.read_long_operand(offset + 1)
+        } else {
+            self.bytecodes[offset + 1].into()
+        }
+    }
+
+    // Bytes taken by the instruction at offset, with the upvalue pairs
+    // trailing a closure
+    pub fn instruction_len(&self, offset: usize) -> usize {
+        let opcode = OpCode::from(self.bytecodes[offset]);
+        match opcode {
+            OpCode::Closure | OpCode::ClosureLong => {
+                let upvalue_count = match self.constant_pool.get(self.constant_operand(offset)) {
+                    Value::Function(function) => function.upvalue_count,
+                    _ => 0,
+                };
+                opcode.to_offset() + upvalue_count * 2
+            }
+            _ => opcode.to_offset(),
+        }
+    }
+
+    // How many values the instruction at offset pushes, less the ones it
+    // pops, when execution carries on past it
+    pub fn stack_effect(&self, offset: usize) -> isize {
+        use OpCode::*;
+
+        let opcode = OpCode::from(self.bytecodes[offset]);
+        let byte = |at: usize| self.bytecodes[offset + at] as isize;
+        match opcode {
+            Constant | ConstantLong | None | True | False | GetLocal | GetGlobal
+            | GetGlobalLong | Struct | StructLong | Closure | ClosureLong | GetUpvalue | Import
+            | ImportLong => 1,
+            Pop | DefineGlobal | DefineGlobalLong | SetProperty | SetPropertyLong | Equal
+            | Greater | Less | Add | Subtract | Multiply | Divide | Modulo | Power | Print
+            | Return | Method | MethodLong | StaticMethod | StaticMethodLong | CloseUpvalue
+            | Inherit | GetSuper | GetSuperLong | GetIndex | Throw | Range | RangeInclusive
+            | IsInstance => -1,
+            SetIndex | EndFinally | IsVariant => -2,
+            Call => -byte(1),
+            Invoke => -byte(2),
+            InvokeLong => -byte(4),
+            SuperInvoke => -byte(2) - 1,
+            SuperInvokeLong => -byte(4) - 1,
+            BuildList => 1 - byte(1),
+            BuildMap => 1 - 2 * byte(1),
+            BuildInstance => -2 * byte(1),
+            Dup => byte(1),
+            ForIter => byte(2),
+            Field => -byte(2),
+            FieldLong => -byte(4),
+            SetLocal | SetGlobal | SetGlobalLong | GetProperty | GetPropertyLong | Not | Negate
+            | Jump | JumpIfFalse | Loop | End | SetUpvalue | Stringify | PushHandler
+            | PopHandler | Iter | MatchList | MatchError | GetPayload | Unknown => 0,
+        }
     }
 }
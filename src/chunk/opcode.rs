@@ -1,5 +1,6 @@
 use std::fmt::Display;
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum OpCode {
     Constant,
     None,
@@ -34,6 +35,51 @@ pub enum OpCode {
     Modulo,
     Power,
     End,
+    Closure,
+    GetUpvalue,
+    SetUpvalue,
+    CloseUpvalue,
+    Inherit,
+    GetSuper,
+    SuperInvoke,
+    BuildList,
+    GetIndex,
+    SetIndex,
+    BuildMap,
+    Import,
+    Stringify,
+    ConstantLong,
+    GetGlobalLong,
+    SetGlobalLong,
+    DefineGlobalLong,
+    GetPropertyLong,
+    SetPropertyLong,
+    InvokeLong,
+    StructLong,
+    MethodLong,
+    ClosureLong,
+    GetSuperLong,
+    SuperInvokeLong,
+    ImportLong,
+    PushHandler,
+    PopHandler,
+    Throw,
+    EndFinally,
+    Range,
+    RangeInclusive,
+    Iter,
+    ForIter,
+    Dup,
+    IsInstance,
+    MatchList,
+    MatchError,
+    IsVariant,
+    GetPayload,
+    Field,
+    FieldLong,
+    BuildInstance,
+    StaticMethod,
+    StaticMethodLong,
     Unknown,
 }
 
@@ -74,14 +120,59 @@ impl From<u8> for OpCode {
             30 => Modulo,
             31 => Power,
             32 => End,
+            33 => Closure,
+            34 => GetUpvalue,
+            35 => SetUpvalue,
+            36 => CloseUpvalue,
+            37 => Inherit,
+            38 => GetSuper,
+            39 => SuperInvoke,
+            40 => BuildList,
+            41 => GetIndex,
+            42 => SetIndex,
+            43 => BuildMap,
+            44 => Import,
+            45 => Stringify,
+            46 => ConstantLong,
+            47 => GetGlobalLong,
+            48 => SetGlobalLong,
+            49 => DefineGlobalLong,
+            50 => GetPropertyLong,
+            51 => SetPropertyLong,
+            52 => InvokeLong,
+            53 => StructLong,
+            54 => MethodLong,
+            55 => ClosureLong,
+            56 => GetSuperLong,
+            57 => SuperInvokeLong,
+            58 => ImportLong,
+            59 => PushHandler,
+            60 => PopHandler,
+            61 => Throw,
+            62 => EndFinally,
+            63 => Range,
+            64 => RangeInclusive,
+            65 => Iter,
+            66 => ForIter,
+            67 => Dup,
+            68 => IsInstance,
+            69 => MatchList,
+            70 => MatchError,
+            71 => IsVariant,
+            72 => GetPayload,
+            73 => Field,
+            74 => FieldLong,
+            75 => BuildInstance,
+            76 => StaticMethod,
+            77 => StaticMethodLong,
             _ => Unknown,
         }
     }
 }
 
-impl Into<u8> for OpCode {
-    fn into(self) -> u8 {
-        self as u8
+impl From<OpCode> for u8 {
+    fn from(value: OpCode) -> Self {
+        value as u8
     }
 }
 
@@ -122,13 +213,58 @@ impl Display for OpCode {
             Modulo => write!(f, "Modulo"),
             Power => write!(f, "Power"),
             End => write!(f, "End"),
+            Closure => write!(f, "Closure"),
+            GetUpvalue => write!(f, "GetUpvalue"),
+            SetUpvalue => write!(f, "SetUpvalue"),
+            CloseUpvalue => write!(f, "CloseUpvalue"),
+            Inherit => write!(f, "Inherit"),
+            GetSuper => write!(f, "GetSuper"),
+            SuperInvoke => write!(f, "SuperInvoke"),
+            BuildList => write!(f, "BuildList"),
+            GetIndex => write!(f, "GetIndex"),
+            SetIndex => write!(f, "SetIndex"),
+            BuildMap => write!(f, "BuildMap"),
+            Import => write!(f, "Import"),
+            Stringify => write!(f, "Stringify"),
+            ConstantLong => write!(f, "ConstantLong"),
+            GetGlobalLong => write!(f, "GetGlobalLong"),
+            SetGlobalLong => write!(f, "SetGlobalLong"),
+            DefineGlobalLong => write!(f, "DefineGlobalLong"),
+            GetPropertyLong => write!(f, "GetPropertyLong"),
+            SetPropertyLong => write!(f, "SetPropertyLong"),
+            InvokeLong => write!(f, "InvokeLong"),
+            StructLong => write!(f, "StructLong"),
+            MethodLong => write!(f, "MethodLong"),
+            ClosureLong => write!(f, "ClosureLong"),
+            GetSuperLong => write!(f, "GetSuperLong"),
+            SuperInvokeLong => write!(f, "SuperInvokeLong"),
+            ImportLong => write!(f, "ImportLong"),
+            PushHandler => write!(f, "PushHandler"),
+            PopHandler => write!(f, "PopHandler"),
+            Throw => write!(f, "Throw"),
+            EndFinally => write!(f, "EndFinally"),
+            Range => write!(f, "Range"),
+            RangeInclusive => write!(f, "RangeInclusive"),
+            Iter => write!(f, "Iter"),
+            ForIter => write!(f, "ForIter"),
+            Dup => write!(f, "Dup"),
+            IsInstance => write!(f, "IsInstance"),
+            MatchList => write!(f, "MatchList"),
+            MatchError => write!(f, "MatchError"),
+            IsVariant => write!(f, "IsVariant"),
+            GetPayload => write!(f, "GetPayload"),
+            Field => write!(f, "Field"),
+            FieldLong => write!(f, "FieldLong"),
+            BuildInstance => write!(f, "BuildInstance"),
+            StaticMethod => write!(f, "StaticMethod"),
+            StaticMethodLong => write!(f, "StaticMethodLong"),
             Unknown => write!(f, "Unknown"),
         }
     }
 }
 
 impl OpCode {
-    pub fn to_offset(&self) -> usize {
+    pub fn to_offset(self) -> usize {
         use OpCode::*;
         match self {
             Constant => 2,
@@ -164,7 +300,97 @@ impl OpCode {
             Modulo => 1,
             Power => 1,
             End => 1,
+            Closure => 2,
+            GetUpvalue => 2,
+            SetUpvalue => 2,
+            CloseUpvalue => 1,
+            Inherit => 1,
+            GetSuper => 2,
+            SuperInvoke => 3,
+            BuildList => 2,
+            GetIndex => 1,
+            SetIndex => 1,
+            BuildMap => 2,
+            Import => 2,
+            Stringify => 1,
+            ConstantLong => 4,
+            GetGlobalLong => 4,
+            SetGlobalLong => 4,
+            DefineGlobalLong => 4,
+            GetPropertyLong => 4,
+            SetPropertyLong => 4,
+            InvokeLong => 5,
+            StructLong => 4,
+            MethodLong => 4,
+            ClosureLong => 4,
+            GetSuperLong => 4,
+            SuperInvokeLong => 5,
+            ImportLong => 4,
+            PushHandler => 6,
+            PopHandler => 1,
+            Throw => 1,
+            EndFinally => 5,
+            Range => 1,
+            RangeInclusive => 1,
+            Iter => 1,
+            ForIter => 5,
+            Dup => 2,
+            IsInstance => 1,
+            MatchList => 3,
+            MatchError => 1,
+            IsVariant => 3,
+            GetPayload => 2,
+            Field => 3,
+            FieldLong => 5,
+            BuildInstance => 2,
+            StaticMethod => 2,
+            StaticMethodLong => 4,
             Unknown => 1,
         }
     }
+
+    // The variant taking a 24-bit constant index instead of a single byte
+    pub fn to_long(self) -> OpCode {
+        use OpCode::*;
+        match self {
+            Constant => ConstantLong,
+            GetGlobal => GetGlobalLong,
+            SetGlobal => SetGlobalLong,
+            DefineGlobal => DefineGlobalLong,
+            GetProperty => GetPropertyLong,
+            SetProperty => SetPropertyLong,
+            Invoke => InvokeLong,
+            Struct => StructLong,
+            Method => MethodLong,
+            Closure => ClosureLong,
+            GetSuper => GetSuperLong,
+            SuperInvoke => SuperInvokeLong,
+            Import => ImportLong,
+            Field => FieldLong,
+            StaticMethod => StaticMethodLong,
+            _ => Unknown,
+        }
+    }
+
+    pub fn is_long(self) -> bool {
+        use OpCode::*;
+        matches!(
+            self,
+            ConstantLong
+                | GetGlobalLong
+                | SetGlobalLong
+                | DefineGlobalLong
+                | GetPropertyLong
+                | SetPropertyLong
+                | InvokeLong
+                | StructLong
+                | MethodLong
+                | ClosureLong
+                | GetSuperLong
+                | SuperInvokeLong
+                | ImportLong
+                | FieldLong
+                | StaticMethodLong
+        )
+    }
 }
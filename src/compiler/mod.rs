@@ -3,16 +3,20 @@ use self::parser::{
     Parser,
 };
 use crate::{
-    chunk::{opcode::OpCode, Chunk},
+    chunk::{opcode::OpCode, Chunk, MAX_CONSTANTS, MAX_GLOBALS},
+    diagnostic::{Diagnostic, Span},
     object::function_object::FunctionObject,
     scanner::{
         token::{Token, TokenKind},
         Scanner,
     },
-    value::Value,
+    value::{interner::Interner, Value},
+    vm::{globals::Globals, runtime_error::RuntimeError},
 };
 use std::{
     cell::{RefCell, RefMut},
+    error::Error,
+    fmt::Display,
     rc::Rc,
 };
 
@@ -22,15 +26,62 @@ mod parse_literal;
 mod parse_statement;
 mod parser;
 
+#[derive(Debug, PartialEq, Clone)]
 pub enum InterpretError {
-    CompileError,
-    RuntimeError,
+    CompileError(Vec<CompileDiagnostic>),
+    RuntimeError(RuntimeError),
+}
+
+impl Display for InterpretError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpretError::CompileError(diagnostics) => {
+                let errors: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            InterpretError::RuntimeError(error) => {
+                write!(f, "{}", error)?;
+                error
+                    .trace
+                    .iter()
+                    .try_for_each(|frame| write!(f, "\n{}", frame))
+            }
+        }
+    }
+}
+
+impl Error for InterpretError {}
+
+// A diagnostic together with the position of the token it was reported at
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CompileDiagnostic {
+    pub diagnostic: Diagnostic,
+    pub line_number: usize,
+    pub column: usize,
+}
+
+impl CompileDiagnostic {
+    pub fn render(&self, source: &str) -> String {
+        self.diagnostic.render(source)
+    }
+}
+
+impl Display for CompileDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[line {}:{}] {}: {}",
+            self.line_number, self.column, self.diagnostic.severity, self.diagnostic.message
+        )
+    }
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum FunctionKind {
     Function,
+    Anonymous,
     Method,
+    Static,
     Script,
     Initializer,
 }
@@ -38,6 +89,8 @@ pub enum FunctionKind {
 pub struct ClassCompiler {
     pub enclosing: RefCell<Option<Rc<ClassCompiler>>>,
     pub has_superclass: RefCell<bool>,
+    // Set while compiling a static function, which has no 'self'
+    pub in_static: RefCell<bool>,
 }
 
 impl ClassCompiler {
@@ -45,67 +98,170 @@ impl ClassCompiler {
         Self {
             enclosing: RefCell::new(None),
             has_superclass: RefCell::new(false),
+            in_static: RefCell::new(false),
         }
     }
 }
 
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum LoopExit {
+    Break,
+    Continue,
+}
+
+// A try statement being compiled inside a loop: the locals it started with,
+// and the breaks and continues jumping to its finally block
+pub struct TryExits {
+    pub slots: usize,
+    pub jumps: Vec<(LoopExit, usize)>,
+}
+
 pub struct Local {
     pub name: Token,
     pub depth: Option<usize>,
+    pub is_captured: bool,
 }
 
 impl Local {
     pub fn new(name: Token, depth: Option<usize>) -> Self {
-        Self { name, depth }
+        Self {
+            name,
+            depth,
+            is_captured: false,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct Upvalue {
+    pub index: u8,
+    pub is_local: bool,
+}
+
+pub struct FunctionCompiler {
+    pub enclosing: Option<Rc<FunctionCompiler>>,
+    pub locals: RefCell<Vec<Local>>,
+    pub upvalues: RefCell<Vec<Upvalue>>,
+}
+
+impl FunctionCompiler {
+    pub fn new(enclosing: Option<Rc<FunctionCompiler>>) -> Self {
+        Self {
+            enclosing,
+            locals: RefCell::new(vec![Local::new(
+                Token {
+                    kind: TokenKind::Identifier,
+                    lexeme: "".to_string(),
+                    line_number: 1,
+                    column: 0,
+                    span: Span::default(),
+                },
+                Some(0),
+            )]),
+            upvalues: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn resolve_local(&self, name: &str) -> Option<(u8, bool)> {
+        self.locals
+            .borrow()
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, local)| name == local.name.lexeme)
+            .map(|(i, local)| (i as u8, local.depth.is_some()))
+    }
+
+    pub fn resolve_upvalue(&self, name: &str) -> Option<u8> {
+        let enclosing = self.enclosing.as_ref()?;
+        if let Some((index, _)) = enclosing.resolve_local(name) {
+            enclosing.locals.borrow_mut()[index as usize].is_captured = true;
+            return Some(self.add_upvalue(index, true));
+        }
+
+        let index = enclosing.resolve_upvalue(name)?;
+        Some(self.add_upvalue(index, false))
+    }
+
+    fn add_upvalue(&self, index: u8, is_local: bool) -> u8 {
+        let upvalue = Upvalue { index, is_local };
+        let mut upvalues = self.upvalues.borrow_mut();
+        if let Some(i) = upvalues.iter().position(|u| *u == upvalue) {
+            return i as u8;
+        }
+        upvalues.push(upvalue);
+        (upvalues.len() - 1) as u8
     }
 }
 
 pub struct Compiler {
     parser: Rc<RefCell<Parser>>,
     scanner: Rc<RefCell<Scanner>>,
+    source: Rc<str>,
     function: FunctionObject,
     kind: FunctionKind,
     rules: Rc<Rules>,
+    interner: Rc<RefCell<Interner>>,
+    globals: Rc<RefCell<Globals>>,
     current_class: Rc<RefCell<Option<Rc<ClassCompiler>>>>,
-    pub locals: Vec<Local>,
+    current_function: Rc<FunctionCompiler>,
     pub scope_depth: usize,
     loop_start: Option<usize>,
     loop_depth: usize,
+    // Exception handlers active in this function, and how many of them
+    // were already active when the innermost loop started
+    handler_depth: usize,
+    loop_handler_depth: usize,
+    try_exits: Vec<TryExits>,
+    returns_last_value: bool,
+    last_value_pop: Option<usize>,
+    // Where the expression an infix handler continues began, and the start
+    // of the span the next emitted bytes are attributed to
+    expression_start: usize,
+    span_start: Option<usize>,
+    // Off where a '{' after an expression opens a block instead
+    allow_struct_literals: bool,
+    // A bytecode offset and how many values are on the stack there, which
+    // stack_height works forward from
+    stack_mark: (usize, usize),
 }
 
 impl Compiler {
-    pub fn new(kind: FunctionKind) -> Self {
+    pub fn new(
+        kind: FunctionKind,
+        interner: Rc<RefCell<Interner>>,
+        globals: Rc<RefCell<Globals>>,
+    ) -> Self {
         let mut result = Self {
             parser: Rc::new(RefCell::new(Parser::new())),
             scanner: Rc::new(RefCell::new(Scanner::new(""))),
+            source: "".into(),
             rules: Rc::new(Rules::new()),
+            interner,
+            globals,
             function: FunctionObject::new(),
             current_class: Rc::new(RefCell::new(None)),
             kind,
             loop_start: None,
             loop_depth: 0,
-            locals: {
-                let mut locals = Vec::new();
-                locals.push(Local::new(
-                    Token {
-                        kind: TokenKind::Identifier,
-                        lexeme: "".to_string(),
-                        line_number: 1,
-                    },
-                    Some(0),
-                ));
-                locals
-            },
+            handler_depth: 0,
+            loop_handler_depth: 0,
+            try_exits: Vec::new(),
+            current_function: Rc::new(FunctionCompiler::new(None)),
             scope_depth: 0,
+            returns_last_value: false,
+            last_value_pop: None,
+            expression_start: 0,
+            span_start: None,
+            allow_struct_literals: true,
+            stack_mark: (0, 1),
         };
         if kind != FunctionKind::Script {
             let name = result.parser().previous.lexeme.clone();
             result.function.name = name;
         }
         if kind != FunctionKind::Function {
-            result.locals[0].name.lexeme = "self".to_string();
-        } else {
-            result.locals[0].name.lexeme = "".to_string();
+            result.locals()[0].name.lexeme = "self".to_string();
         }
 
         result
@@ -115,34 +271,39 @@ impl Compiler {
         let mut result = Self {
             parser: self.parser.clone(),
             scanner: self.scanner.clone(),
+            source: self.source.clone(),
             rules: self.rules.clone(),
+            interner: self.interner.clone(),
+            globals: self.globals.clone(),
             function: FunctionObject::new(),
             kind,
             loop_start: None,
             loop_depth: 0,
+            handler_depth: 0,
+            loop_handler_depth: 0,
+            try_exits: Vec::new(),
             current_class: self.current_class.clone(),
-            locals: {
-                let mut locals = Vec::new();
-                locals.push(Local::new(
-                    Token {
-                        kind: TokenKind::Identifier,
-                        lexeme: "".to_string(),
-                        line_number: 1,
-                    },
-                    Some(0),
-                ));
-                locals
-            },
+            current_function: Rc::new(FunctionCompiler::new(Some(self.current_function.clone()))),
             scope_depth: 0,
+            returns_last_value: false,
+            last_value_pop: None,
+            expression_start: 0,
+            span_start: None,
+            allow_struct_literals: true,
+            stack_mark: (0, 1),
         };
-        if kind != FunctionKind::Script {
+        if kind == FunctionKind::Anonymous {
+            let previous = result.parser().previous.clone();
+            result.function.name = format!("anonymous@{}", previous.line_number);
+        } else if kind != FunctionKind::Script {
             let name = result.parser().previous.lexeme.clone();
             result.function.name = name;
         }
-        if kind != FunctionKind::Function {
-            result.locals[0].name.lexeme = "self".to_string();
-        } else {
-            result.locals[0].name.lexeme = "".to_string();
+        if !matches!(
+            kind,
+            FunctionKind::Function | FunctionKind::Anonymous | FunctionKind::Static
+        ) {
+            result.locals()[0].name.lexeme = "self".to_string();
         }
 
         result
@@ -156,18 +317,44 @@ impl Compiler {
         self.parser.borrow_mut()
     }
 
+    fn locals(&self) -> RefMut<'_, Vec<Local>> {
+        self.current_function.locals.borrow_mut()
+    }
+
     fn rules(&self) -> &Rc<Rules> {
         &self.rules
     }
 
-    pub fn compile(mut self, source: &str) -> Result<FunctionObject, InterpretError> {
+    // Make the script return the value of a trailing expression statement
+    pub fn return_last_value(&mut self) {
+        self.returns_last_value = true;
+    }
+
+    // Statements end with ';', except the last one of evaluated input, so
+    // the REPL takes `P { x: 1 }` or `let m = {}` as typed
+    fn consume_statement_end(&mut self, message: &str) {
+        if self.returns_last_value && self.scope_depth == 0 && self.check(TokenKind::EOF) {
+            return;
+        }
+        self.consume(TokenKind::Semicolon, message);
+    }
+
+    pub fn compile(mut self, source: &str) -> Result<FunctionObject, Vec<CompileDiagnostic>> {
         *self.scanner() = Scanner::new(source);
+        self.source = source.into();
         self.advance();
         while !self.matches(TokenKind::EOF) {
             self.parse_declaration();
         }
-        if self.parser().had_error.get() {
-            Err(InterpretError::CompileError)
+        let last = self.current_chunk().bytecodes.len().checked_sub(1);
+        if self.last_value_pop.is_some() && self.last_value_pop == last {
+            self.current_chunk().bytecodes.pop();
+            self.current_chunk().line_numbers.pop();
+            self.current_chunk().spans.pop();
+            self.emit_one_byte(OpCode::Return);
+        }
+        if self.parser().had_error() {
+            Err(self.parser().diagnostics.take())
         } else {
             let function = self.end_complier();
             Ok(function)
@@ -182,8 +369,8 @@ impl Compiler {
             if self.parser().current.kind != TokenKind::Error {
                 break;
             }
-            let message = &self.parser().current.lexeme;
-            self.parser().error_at_current(message);
+            let message = self.parser().current.lexeme.clone();
+            self.parser().error_at_current(&message);
         }
     }
 
@@ -204,6 +391,26 @@ impl Compiler {
         true
     }
 
+    // Consumes a compound assignment like +=, returning its arithmetic
+    fn match_compound_assignment(&mut self, can_assign: bool) -> Option<OpCode> {
+        use TokenKind::*;
+
+        if !can_assign {
+            return Option::None;
+        }
+        let operator = match self.parser().current.kind {
+            PlusEqual => OpCode::Add,
+            MinusEqual => OpCode::Subtract,
+            StarEqual => OpCode::Multiply,
+            SlashEqual => OpCode::Divide,
+            ModuloEqual => OpCode::Modulo,
+            PowerEqual => OpCode::Power,
+            _ => return Option::None,
+        };
+        self.advance();
+        Some(operator)
+    }
+
     fn check(&self, kind: TokenKind) -> bool {
         self.parser().current.kind == kind
     }
@@ -214,7 +421,20 @@ impl Compiler {
 
     fn emit_one_byte<T: Into<u8>>(&mut self, byte: T) {
         let line_number = self.parser().previous.line_number;
-        self.current_chunk().push_bytecode(byte.into(), line_number);
+        let mut span = self.parser().previous.span;
+        if let Some(start) = self.span_start {
+            span.start = start;
+        }
+        self.current_chunk()
+            .push_bytecode(byte.into(), line_number, span);
+    }
+
+    // Attribute the bytes emitted by `emit` to everything parsed since
+    // `start`, so runtime errors underline the whole expression
+    fn emit_spanning(&mut self, start: usize, emit: impl FnOnce(&mut Self)) {
+        let enclosing = self.span_start.replace(start);
+        emit(self);
+        self.span_start = enclosing;
     }
 
     fn emit_two_bytes<T: Into<u8>, U: Into<u8>>(&mut self, byte1: T, byte2: U) {
@@ -235,9 +455,11 @@ impl Compiler {
 
     fn end_complier(mut self) -> FunctionObject {
         self.emit_return();
+        self.function.chunk.source = Some(self.source.clone());
+        self.function.upvalue_count = self.current_function.upvalues.borrow().len();
 
         #[cfg(feature = "debug_mode")]
-        if !self.parser().had_error.get() {
+        if !self.parser().had_error() {
             self.current_chunk().disassemble_chunk("<script>");
         }
 
@@ -251,39 +473,98 @@ impl Compiler {
     fn end_scope(&mut self) {
         self.scope_depth -= 1;
 
-        while self.locals.len() > 0
-            && self
-                .locals
-                .last()
-                .unwrap()
-                .depth
-                .is_some_and(|depth| depth > self.scope_depth)
-        {
-            self.emit_one_byte(OpCode::Pop);
-            self.locals.pop();
+        loop {
+            let is_captured = match self.locals().last() {
+                Some(local) if local.depth.is_some_and(|depth| depth > self.scope_depth) => {
+                    local.is_captured
+                }
+                _ => break,
+            };
+            self.emit_pop_local(is_captured);
+            self.locals().pop();
         }
     }
 
-    fn make_constant(&mut self, value: Value) -> u8 {
-        if let Some(index) = self.current_chunk().push_constant(value) {
-            index
+    // Statements start with exactly the locals on the stack
+    fn mark_stack_height(&mut self, height: usize) {
+        self.stack_mark = (self.current_chunk().bytecodes.len(), height);
+    }
+
+    // Values on the stack at the end of the bytecode so far, counting the
+    // temporaries of unfinished expressions
+    fn stack_height(&mut self) -> usize {
+        let (mut offset, height) = self.stack_mark;
+        let mut height = height as isize;
+        while offset < self.current_chunk().bytecodes.len() {
+            height += self.current_chunk().stack_effect(offset);
+            offset += self.current_chunk().instruction_len(offset);
+        }
+        height as usize
+    }
+
+    fn emit_pop_local(&mut self, is_captured: bool) {
+        if is_captured {
+            self.emit_one_byte(OpCode::CloseUpvalue);
         } else {
-            0
+            self.emit_one_byte(OpCode::Pop);
         }
     }
 
+    fn make_constant(&mut self, value: Value) -> usize {
+        let index = self.current_chunk().push_constant(value);
+        if index >= MAX_CONSTANTS {
+            self.parser().error("Too many constants in one chunk.");
+            return 0;
+        }
+        index
+    }
+
     fn emit_constant(&mut self, value: Value) {
         let index = self.make_constant(value);
-        self.emit_two_bytes(OpCode::Constant, index);
+        self.emit_operand(OpCode::Constant, index);
     }
 
-    fn emit_identifier_constant(&mut self, name: String) -> u8 {
+    // Falls back to the wide variant of the opcode when the index needs it.
+    // Only opcodes with a wide variant can take an index past 255
+    fn emit_operand(&mut self, opcode: OpCode, index: usize) {
+        match u8::try_from(index) {
+            Ok(index) => self.emit_two_bytes(opcode, index),
+            Err(_) => {
+                assert!(
+                    opcode.to_long() != OpCode::Unknown,
+                    "opcode {} has no wide variant",
+                    opcode
+                );
+                self.emit_one_byte(opcode.to_long());
+                self.emit_one_byte((index >> 16) as u8);
+                self.emit_two_bytes((index >> 8) as u8, index as u8);
+            }
+        }
+    }
+
+    fn emit_identifier_constant(&mut self, name: String) -> usize {
+        let name = self.intern(&name);
         self.make_constant(Value::String(name))
     }
 
+    fn intern(&self, string: &str) -> Rc<str> {
+        self.interner.borrow_mut().intern(string)
+    }
+
+    fn global_slot(&mut self, name: &str) -> usize {
+        let name = self.intern(name);
+        let slot = self.globals.borrow_mut().resolve(name);
+        if slot >= MAX_GLOBALS {
+            self.parser().error("Too many global variables.");
+            return 0;
+        }
+        slot
+    }
+
     fn parse_precedence(&mut self, precedence: Precedence) {
         self.advance();
         let kind = self.parser().previous.kind;
+        let start = self.parser().previous.span.start;
         if let Some(prefix_handler) = self.rules().get(kind).prefix_handler {
             let can_assign = precedence <= Precedence::Assignment;
             prefix_handler(self, can_assign);
@@ -291,9 +572,13 @@ impl Compiler {
                 self.advance();
                 let kind = self.parser().previous.kind;
                 if let Some(infix_handler) = self.rules().get(kind).infix_handler {
+                    self.expression_start = start;
                     infix_handler(self, can_assign);
                 }
-                if can_assign && self.matches(TokenKind::Equal) {
+                if can_assign
+                    && (self.matches(TokenKind::Equal)
+                        || self.match_compound_assignment(can_assign).is_some())
+                {
                     self.parser().error("Invalid assignment target.");
                 }
             }
@@ -315,17 +600,29 @@ impl Compiler {
             let kind = self.parser().current.kind;
 
             match kind {
-                Struct | Fn | Let | For | If | While | Print | Return => return,
+                Struct | Enum | Static | Fn | Let | Import | For | If | While | Print | Return
+                | Try | Throw => return,
                 _ => self.advance(),
             }
         }
     }
 
     fn add_local(&mut self, name: Token) {
-        if self.locals.len() == u8::MAX.into() {
+        if self.locals().len() == u8::MAX.into() {
             self.parser().error("Too many local variables in function.");
             return;
         }
-        self.locals.push(Local::new(name, None));
+        self.locals().push(Local::new(name, None));
+    }
+
+    // A local the compiler keeps for itself, which no identifier resolves to
+    fn add_hidden_local(&mut self) {
+        let name = Token {
+            kind: TokenKind::Identifier,
+            lexeme: String::new(),
+            ..self.parser().previous.clone()
+        };
+        self.add_local(name);
+        self.mark_initialized();
     }
 }
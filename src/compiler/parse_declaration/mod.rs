@@ -1,18 +1,28 @@
 use super::Compiler;
 use crate::scanner::token::TokenKind;
 
+mod parse_enum_declaration;
 mod parse_fn_declaration;
+mod parse_import_declaration;
 mod parse_let_declaration;
 mod parse_struct_declaration;
 
 impl Compiler {
     pub fn parse_declaration(&mut self) {
-        if self.matches(TokenKind::Fn) {
+        let height = self.locals().len();
+        self.mark_stack_height(height);
+        // fn( starts an anonymous function rather than a declaration
+        if self.check(TokenKind::Fn) && self.scanner().peek_token(0).kind != TokenKind::LeftParen {
+            self.advance();
             self.parse_fn_declaration();
         } else if self.matches(TokenKind::Struct) {
             self.parse_struct_declaration();
+        } else if self.matches(TokenKind::Enum) {
+            self.parse_enum_declaration();
         } else if self.matches(TokenKind::Let) {
             self.parse_let_declaration();
+        } else if self.matches(TokenKind::Import) {
+            self.parse_import_declaration();
         } else {
             self.parse_statement();
         }
@@ -0,0 +1,66 @@
+use crate::{
+    compiler::Compiler,
+    object::enum_object::{EnumObject, EnumVariant},
+    scanner::token::TokenKind,
+    value::Value,
+};
+use std::rc::Rc;
+
+impl Compiler {
+    pub fn parse_enum_declaration(&mut self) {
+        use TokenKind::*;
+
+        let global = self.parse_variable_name("Expect enum name.");
+        let enum_name = self.parser().previous.lexeme.clone();
+        self.consume(LeftBrace, "Expect '{' before enum body.");
+        let mut variants: Vec<EnumVariant> = Vec::new();
+        while !self.check(RightBrace) && !self.check(EOF) {
+            self.consume(Identifier, "Expect variant name.");
+            let name = self.intern(&self.parser().previous.lexeme.clone());
+            if variants.iter().any(|variant| variant.name == name) {
+                self.parser()
+                    .error("Already a variant with this name in this enum.");
+            }
+            let fields = if self.matches(LeftParen) {
+                self.parse_variant_fields()
+            } else {
+                Vec::new()
+            };
+            variants.push(EnumVariant { name, fields });
+            if !self.matches(Comma) {
+                break;
+            }
+        }
+        self.consume(RightBrace, "Expect '}' after enum body.");
+
+        let r#enum = EnumObject::new(enum_name, variants);
+        self.emit_constant(Value::Enum(Rc::new(r#enum)));
+        self.define_variable(global);
+    }
+
+    fn parse_variant_fields(&mut self) -> Vec<Rc<str>> {
+        use TokenKind::*;
+
+        let mut fields: Vec<Rc<str>> = Vec::new();
+        if !self.check(RightParen) {
+            loop {
+                self.consume(Identifier, "Expect field name.");
+                let field = self.intern(&self.parser().previous.lexeme.clone());
+                if fields.contains(&field) {
+                    self.parser()
+                        .error("Already a field with this name in this variant.");
+                }
+                fields.push(field);
+                if fields.len() > u8::MAX.into() {
+                    self.parser()
+                        .error("Can't have more than 255 fields in a variant.");
+                }
+                if !self.matches(Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(RightParen, "Expect ')' after variant fields.");
+        fields
+    }
+}
@@ -21,26 +21,38 @@ impl Compiler {
         compiler.begin_scope();
         compiler.consume(LeftParen, "Expect '(' after function name.");
         if !compiler.check(RightParen) {
-            loop {
-                compiler.function.arity += 1;
-                if compiler.function.arity > u8::MAX.into() {
-                    compiler
-                        .parser()
-                        .error_at_current("Can't have more than 255 parameters.");
-                }
-                let constant = compiler.parse_variable_name("Expect parameter name.");
-                compiler.define_variable(constant);
-
-                if !compiler.matches(Comma) {
-                    break;
-                }
-            }
+            compiler.parse_parameters();
         }
         compiler.consume(RightParen, "Expect ')' after parameters.");
         compiler.consume(LeftBrace, "Expect '{' before function body.");
         compiler.parse_block_statement();
+        self.emit_closure(compiler);
+    }
+
+    pub fn parse_parameters(&mut self) {
+        loop {
+            self.function.arity += 1;
+            if self.function.arity > u8::MAX.into() {
+                self.parser()
+                    .error_at_current("Can't have more than 255 parameters.");
+            }
+            let constant = self.parse_variable_name("Expect parameter name.");
+            self.define_variable(constant);
+
+            if !self.matches(TokenKind::Comma) {
+                break;
+            }
+        }
+    }
+
+    pub fn emit_closure(&mut self, compiler: Compiler) {
+        let function_compiler = compiler.current_function.clone();
         let function = compiler.end_complier();
+        let upvalues = function_compiler.upvalues.take();
         let value = self.make_constant(Value::Function(Rc::new(function)));
-        self.emit_two_bytes(OpCode::Constant, value)
+        self.emit_operand(OpCode::Closure, value);
+        for upvalue in upvalues {
+            self.emit_two_bytes(upvalue.is_local as u8, upvalue.index);
+        }
     }
 }
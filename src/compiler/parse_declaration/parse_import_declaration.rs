@@ -0,0 +1,54 @@
+use super::Compiler;
+use crate::{
+    chunk::opcode::OpCode,
+    scanner::{
+        string_literal::string_value,
+        token::{Token, TokenKind},
+    },
+    value::Value,
+};
+use std::path::Path;
+
+impl Compiler {
+    pub fn parse_import_declaration(&mut self) {
+        use TokenKind::*;
+
+        self.consume(String, "Expect module path after 'import'.");
+        let path_token = self.parser().previous.clone();
+        let path = string_value(&path_token.lexeme);
+        let path_constant = self.make_constant(Value::String(self.intern(&path)));
+
+        let name = if self.matches(As) {
+            self.consume(Identifier, "Expect module name after 'as'.");
+            self.parser().previous.clone()
+        } else {
+            let stem = Path::new(&path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if !is_identifier(&stem) {
+                self.parser().error_with_help(
+                    &format!("Module name '{}' is not a valid identifier.", stem),
+                    "name the module with 'as', e.g. import \"path\" as name;",
+                );
+            }
+            Token {
+                kind: Identifier,
+                lexeme: stem,
+                ..path_token
+            }
+        };
+        self.consume_statement_end("Expect ';' after import.");
+
+        self.declare_local(name.clone());
+        let global = self.global_variable(&name.lexeme);
+        self.emit_operand(OpCode::Import, path_constant);
+        self.define_variable(global);
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
@@ -1,5 +1,8 @@
 use super::Compiler;
-use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+use crate::{
+    chunk::opcode::OpCode,
+    scanner::token::{Token, TokenKind},
+};
 
 impl Compiler {
     pub fn parse_let_declaration(&mut self) {
@@ -11,43 +14,52 @@ impl Compiler {
         } else {
             self.emit_one_byte(OpCode::None);
         }
-        self.consume(Semicolon, "Expect ';' after variable declaration.");
+        self.consume_statement_end("Expect ';' after variable declaration.");
         self.define_variable(global);
     }
 
-    pub fn parse_variable_name(&mut self, error_message: &str) -> u8 {
+    pub fn parse_variable_name(&mut self, error_message: &str) -> usize {
         self.consume(TokenKind::Identifier, error_message);
 
         self.declare_variable();
+        let name = self.parser().previous.lexeme.clone();
+        self.global_variable(&name)
+    }
+
+    // Slot operand for DefineGlobal, unused when declaring a local
+    pub fn global_variable(&mut self, name: &str) -> usize {
         if self.scope_depth > 0 {
             return 0;
         }
-        let name = self.parser().previous.lexeme.clone();
-        self.emit_identifier_constant(name)
+        self.global_slot(name)
     }
 
-    pub fn define_variable(&mut self, global: u8) {
+    pub fn define_variable(&mut self, global: usize) {
         if self.scope_depth > 0 {
             self.mark_initialized();
             return;
         }
-        self.emit_two_bytes(OpCode::DefineGlobal, global);
+        self.emit_operand(OpCode::DefineGlobal, global);
     }
 
     pub fn declare_variable(&mut self) {
+        let name = self.parser().previous.clone();
+        self.declare_local(name);
+    }
+
+    pub fn declare_local(&mut self, name: Token) {
         if self.scope_depth == 0 {
             return;
         }
-        let name = self.parser().previous.clone();
-        for local in self.locals.iter().rev() {
-            if local.depth.is_some() && local.depth.is_some_and(|depth| depth < self.scope_depth) {
-                break;
-            }
-
-            if name == local.name {
-                self.parser()
-                    .error("Already a variable with this name in this scope.");
-            }
+        let is_redeclared = self
+            .locals()
+            .iter()
+            .rev()
+            .take_while(|local| local.depth.is_none_or(|depth| depth >= self.scope_depth))
+            .any(|local| name.lexeme == local.name.lexeme);
+        if is_redeclared {
+            self.parser()
+                .error("Already a variable with this name in this scope.");
         }
         self.add_local(name);
     }
@@ -57,6 +69,6 @@ impl Compiler {
             return;
         }
         let scope_depth = Some(self.scope_depth);
-        self.locals.last_mut().unwrap().depth = scope_depth;
+        self.locals().last_mut().unwrap().depth = scope_depth;
     }
 }
@@ -1,7 +1,7 @@
 use crate::{
     chunk::opcode::OpCode,
     compiler::{ClassCompiler, Compiler, FunctionKind},
-    scanner::token::TokenKind,
+    scanner::token::{Token, TokenKind},
 };
 use std::rc::Rc;
 
@@ -13,8 +13,9 @@ impl Compiler {
         let struct_name = self.parser().previous.lexeme.clone();
         let name_constant = self.emit_identifier_constant(struct_name.clone());
         self.declare_variable();
-        self.emit_two_bytes(OpCode::Struct, name_constant);
-        self.define_variable(name_constant);
+        let global = self.global_variable(&struct_name);
+        self.emit_operand(OpCode::Struct, name_constant);
+        self.define_variable(global);
 
         let prev = self
             .current_class
@@ -26,14 +27,57 @@ impl Compiler {
             .enclosing
             .replace(prev);
 
+        if self.matches(Colon) {
+            self.consume(Identifier, "Expect parent struct name.");
+            let parent_name = self.parser().previous.lexeme.clone();
+            self.parse_named_variable(parent_name, false);
+            if struct_name == self.parser().previous.lexeme {
+                self.parser().error("A struct can't inherit from itself.");
+            }
+
+            self.begin_scope();
+            let parent = self.parser().previous.clone();
+            self.add_local(Token {
+                kind: Super,
+                lexeme: "super".to_string(),
+                ..parent
+            });
+            self.define_variable(0);
+
+            self.parse_named_variable(struct_name.clone(), false);
+            self.emit_one_byte(OpCode::Inherit);
+            self.current_class
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .has_superclass
+                .replace(true);
+        }
+
         self.parse_named_variable(struct_name, false);
         self.consume(LeftBrace, "Expect '{' before struct body.");
+        let mut fields = Vec::new();
         while !self.check(RightBrace) && !self.check(EOF) {
-            self.parse_method();
+            if self.check(Identifier) {
+                self.parse_fields(&mut fields);
+            } else {
+                self.parse_method();
+            }
         }
         self.consume(RightBrace, "Expect '}' before struct body.");
         self.emit_one_byte(OpCode::Pop);
 
+        if *self
+            .current_class
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .has_superclass
+            .borrow()
+        {
+            self.end_scope();
+        }
+
         let prev = self
             .current_class
             .borrow()
@@ -44,19 +88,65 @@ impl Compiler {
         self.current_class.replace(prev);
     }
 
+    // x, y = 0; declares fields. Each default compiles into a function run
+    // for every new instance
+    fn parse_fields(&mut self, fields: &mut Vec<String>) {
+        use TokenKind::*;
+
+        loop {
+            self.consume(Identifier, "Expect field name.");
+            let name = self.parser().previous.lexeme.clone();
+            if fields.contains(&name) {
+                self.parser()
+                    .error("Already a field with this name in this struct.");
+            }
+            fields.push(name.clone());
+            let constant = self.emit_identifier_constant(name.clone());
+            let has_default = self.matches(Equal);
+            if has_default {
+                let mut compiler = self.fork(FunctionKind::Function);
+                compiler.function.name = name;
+                compiler.begin_scope();
+                compiler.parse_expression();
+                compiler.emit_one_byte(OpCode::Return);
+                self.emit_closure(compiler);
+            }
+            self.emit_operand(OpCode::Field, constant);
+            self.emit_one_byte(has_default as u8);
+            if !self.matches(Comma) {
+                break;
+            }
+        }
+        self.consume(Semicolon, "Expect ';' after fields.");
+    }
+
     fn parse_method(&mut self) {
         use FunctionKind::*;
         use TokenKind::*;
 
+        let is_static = self.matches(TokenKind::Static);
         self.consume(Fn, "Expect fn keyword.");
         self.consume(Identifier, "Expect method name.");
         let name = self.parser().previous.lexeme.clone();
         let constant = self.emit_identifier_constant(name);
+        if is_static {
+            self.parse_static_function(constant);
+            return;
+        }
         let mut kind = Method;
         if self.parser().previous.lexeme == "new" {
             kind = Initializer;
         }
         self.parse_fn_body(kind);
-        self.emit_two_bytes(OpCode::Method, constant);
+        self.emit_operand(OpCode::Method, constant);
+    }
+
+    // static fn origin() { ... } is called on the struct itself, without self
+    fn parse_static_function(&mut self, constant: usize) {
+        let class = self.current_class.borrow().clone().unwrap();
+        class.in_static.replace(true);
+        self.parse_fn_body(FunctionKind::Static);
+        class.in_static.replace(false);
+        self.emit_operand(OpCode::StaticMethod, constant);
     }
 }
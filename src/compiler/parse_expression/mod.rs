@@ -4,9 +4,14 @@ mod parse_and_expression;
 mod parse_binary_expression;
 mod parse_dot_expression;
 mod parse_fn_call_expression;
+mod parse_fn_expression;
 mod parse_grouping_expression;
+mod parse_index_expression;
+mod parse_match_expression;
 mod parse_or_expression;
+mod parse_path_expression;
 mod parse_self_expression;
+mod parse_super_expression;
 mod parse_unary_expression;
 mod parse_variable_expression;
 
@@ -14,4 +19,19 @@ impl Compiler {
     pub fn parse_expression(&mut self) {
         self.parse_precedence(Precedence::Assignment)
     }
+
+    // For a for-in iterable or a match subject, where Name { would be read
+    // as a struct literal instead of the block that follows
+    pub fn parse_expression_before_block(&mut self) {
+        let allowed = std::mem::replace(&mut self.allow_struct_literals, false);
+        self.parse_expression();
+        self.allow_struct_literals = allowed;
+    }
+
+    // Brackets end any doubt, so struct literals are allowed again inside
+    pub fn parse_nested_expression(&mut self) {
+        let allowed = std::mem::replace(&mut self.allow_struct_literals, true);
+        self.parse_expression();
+        self.allow_struct_literals = allowed;
+    }
 }
@@ -6,24 +6,27 @@ impl Compiler {
     pub fn parse_binary_expression(&mut self, _can_assign: bool) {
         use OpCode::*;
         use TokenKind::*;
+        let start = self.expression_start;
         let binary_operator = self.parser().previous.kind;
         let precedence: u8 = self.rules().get(binary_operator).precedence.into();
         self.parse_precedence((precedence + 1).into());
 
-        match binary_operator {
-            BangEqual => self.emit_two_bytes(OpCode::Equal, Not),
-            EqualEqual => self.emit_one_byte(OpCode::Equal),
-            TokenKind::Greater => self.emit_one_byte(OpCode::Greater),
-            GreaterEqual => self.emit_two_bytes(OpCode::Less, Not),
-            TokenKind::Less => self.emit_one_byte(OpCode::Less),
-            LessEqual => self.emit_two_bytes(OpCode::Greater, Not),
-            Plus => self.emit_one_byte(Add),
-            Minus => self.emit_one_byte(Subtract),
-            Star => self.emit_one_byte(Multiply),
-            Slash => self.emit_one_byte(Divide),
-            TokenKind::Modulo => self.emit_one_byte(OpCode::Modulo),
-            TokenKind::Power => self.emit_one_byte(OpCode::Power),
+        self.emit_spanning(start, |compiler| match binary_operator {
+            BangEqual => compiler.emit_two_bytes(OpCode::Equal, Not),
+            EqualEqual => compiler.emit_one_byte(OpCode::Equal),
+            TokenKind::Greater => compiler.emit_one_byte(OpCode::Greater),
+            GreaterEqual => compiler.emit_two_bytes(OpCode::Less, Not),
+            TokenKind::Less => compiler.emit_one_byte(OpCode::Less),
+            LessEqual => compiler.emit_two_bytes(OpCode::Greater, Not),
+            Plus => compiler.emit_one_byte(Add),
+            Minus => compiler.emit_one_byte(Subtract),
+            Star => compiler.emit_one_byte(Multiply),
+            Slash => compiler.emit_one_byte(Divide),
+            TokenKind::Modulo => compiler.emit_one_byte(OpCode::Modulo),
+            TokenKind::Power => compiler.emit_one_byte(OpCode::Power),
+            DotDot => compiler.emit_one_byte(OpCode::Range),
+            DotDotEqual => compiler.emit_one_byte(RangeInclusive),
             _ => panic!("binary operator not found"),
-        }
+        });
     }
 }
@@ -6,18 +6,29 @@ impl Compiler {
         use OpCode::*;
         use TokenKind::*;
 
+        let start = self.expression_start;
         self.consume(Identifier, "Expect property name after '.'.");
         let name = self.parser().previous.lexeme.clone();
         let name = self.emit_identifier_constant(name);
         if can_assign && self.matches(TokenKind::Equal) {
             self.parse_expression();
-            self.emit_two_bytes(SetProperty, name);
+            self.emit_spanning(start, |compiler| compiler.emit_operand(SetProperty, name));
+        } else if let Some(operator) = self.match_compound_assignment(can_assign) {
+            self.emit_two_bytes(Dup, 1);
+            self.emit_spanning(start, |compiler| compiler.emit_operand(GetProperty, name));
+            self.parse_expression();
+            self.emit_spanning(start, |compiler| {
+                compiler.emit_one_byte(operator);
+                compiler.emit_operand(SetProperty, name);
+            });
         } else if self.matches(LeftParen) {
             let arg_count = self.argument_list();
-            self.emit_two_bytes(Invoke, name);
-            self.emit_one_byte(arg_count);
+            self.emit_spanning(start, |compiler| {
+                compiler.emit_operand(Invoke, name);
+                compiler.emit_one_byte(arg_count);
+            });
         } else {
-            self.emit_two_bytes(GetProperty, name);
+            self.emit_spanning(start, |compiler| compiler.emit_operand(GetProperty, name));
         }
     }
 }
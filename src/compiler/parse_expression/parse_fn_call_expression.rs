@@ -3,8 +3,11 @@ use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
 
 impl Compiler {
     pub fn parse_fn_call_expression(&mut self, _can_assign: bool) {
+        let start = self.expression_start;
         let arg_count = self.argument_list();
-        self.emit_two_bytes(OpCode::Call, arg_count);
+        self.emit_spanning(start, |compiler| {
+            compiler.emit_two_bytes(OpCode::Call, arg_count)
+        });
     }
 
     pub fn argument_list(&mut self) -> u8 {
@@ -13,7 +16,7 @@ impl Compiler {
         let mut arg_count = 0;
         if !self.check(RightParen) {
             loop {
-                self.parse_expression();
+                self.parse_nested_expression();
                 if arg_count == u8::MAX {
                     self.parser().error("Can't have more than 255 arguments.");
                 }
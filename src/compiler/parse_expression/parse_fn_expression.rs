@@ -0,0 +1,23 @@
+use super::Compiler;
+use crate::{chunk::opcode::OpCode, compiler::FunctionKind, scanner::token::TokenKind};
+
+impl Compiler {
+    pub fn parse_fn_expression(&mut self, _can_assign: bool) {
+        self.parse_fn_body(FunctionKind::Anonymous);
+    }
+
+    // |x, y| x + y is shorthand for fn(x, y) { return x + y; }
+    pub fn parse_closure_expression(&mut self, _can_assign: bool) {
+        let mut compiler = self.fork(FunctionKind::Anonymous);
+        compiler.begin_scope();
+        if !compiler.matches(TokenKind::Pipe) {
+            compiler.parse_parameters();
+            compiler.consume(TokenKind::Pipe, "Expect '|' after parameters.");
+        }
+        let height = compiler.locals().len();
+        compiler.mark_stack_height(height);
+        compiler.parse_expression();
+        compiler.emit_one_byte(OpCode::Return);
+        self.emit_closure(compiler);
+    }
+}
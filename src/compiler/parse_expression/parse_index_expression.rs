@@ -0,0 +1,25 @@
+use super::Compiler;
+use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+
+impl Compiler {
+    pub fn parse_index_expression(&mut self, can_assign: bool) {
+        use OpCode::*;
+
+        let start = self.expression_start;
+        self.parse_nested_expression();
+        self.consume(TokenKind::RightBracket, "Expect ']' after index.");
+        if can_assign && self.matches(TokenKind::Equal) {
+            self.parse_expression();
+            self.emit_spanning(start, |compiler| compiler.emit_one_byte(SetIndex));
+        } else if let Some(operator) = self.match_compound_assignment(can_assign) {
+            self.emit_two_bytes(Dup, 2);
+            self.emit_spanning(start, |compiler| compiler.emit_one_byte(GetIndex));
+            self.parse_expression();
+            self.emit_spanning(start, |compiler| {
+                compiler.emit_two_bytes(operator, SetIndex)
+            });
+        } else {
+            self.emit_spanning(start, |compiler| compiler.emit_one_byte(GetIndex));
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use super::Compiler;
+use crate::{
+    chunk::opcode::OpCode,
+    scanner::token::{Token, TokenKind},
+    value::Value,
+};
+
+// How to reach part of the matched value: the local holding it, then field
+// name constants, list indexes or variant payload indexes applied in order
+#[derive(Clone)]
+enum Access {
+    Local(u8),
+    Field(usize),
+    Index(usize),
+    Payload(usize),
+}
+
+impl Compiler {
+    // The matched value sits in a hidden local, with the temporaries below
+    // it covered by hidden locals too, so bindings get the right slots. Each
+    // arm tests its pattern, binds, checks its guard and then leaves its
+    // value in the matched value's slot
+    pub fn parse_match_expression(&mut self, _can_assign: bool) {
+        use TokenKind::*;
+
+        let start = self.parser().previous.span.start;
+        let locals = self.locals().len();
+        let height = self.stack_height();
+        self.begin_scope();
+        // The matched value's slot has to fit in a byte too
+        if height >= u8::MAX.into() {
+            self.parser().error("Too many local variables in function.");
+        } else {
+            while self.locals().len() < height {
+                self.add_hidden_local();
+            }
+        }
+        self.parse_expression_before_block();
+        // An uninitialized variable being declared already stands for the
+        // matched value's slot
+        if self.locals().len() == height {
+            self.add_hidden_local();
+        }
+        let subject = height as u8;
+
+        self.consume(LeftBrace, "Expect '{' after match value.");
+        let mut end_jumps = Vec::new();
+        while !self.check(RightBrace) && !self.check(EOF) {
+            end_jumps.push(self.parse_match_arm(subject));
+        }
+        self.consume(RightBrace, "Expect '}' after match arms.");
+        self.emit_spanning(start, |compiler| compiler.emit_one_byte(OpCode::MatchError));
+        for jump in end_jumps {
+            self.patch_jump(jump);
+        }
+        self.locals().truncate(locals);
+        self.scope_depth -= 1;
+        self.mark_stack_height(height + 1);
+    }
+
+    // Returns the jump out of the match taken when the arm matched
+    fn parse_match_arm(&mut self, subject: u8) -> usize {
+        use OpCode::*;
+        use TokenKind::*;
+
+        let locals = self.locals().len();
+        self.mark_stack_height(usize::from(subject) + 1);
+        self.begin_scope();
+        let mut bindings = Vec::new();
+        let fail_jump = self
+            .parse_pattern(&[Access::Local(subject)], &mut bindings)
+            .then(|| {
+                let jump = self.emit_jump(JumpIfFalse);
+                self.emit_one_byte(Pop);
+                jump
+            });
+        for (name, path) in bindings {
+            self.emit_path(&path);
+            self.declare_local(name);
+            self.mark_initialized();
+        }
+        let height = self.locals().len();
+        self.mark_stack_height(height);
+        let guard_jump = self.matches(If).then(|| {
+            self.parse_expression();
+            let jump = self.emit_jump(JumpIfFalse);
+            self.emit_one_byte(Pop);
+            jump
+        });
+
+        self.consume(FatArrow, "Expect '=>' after match pattern.");
+        if self.matches(LeftBrace) {
+            self.begin_scope();
+            self.parse_block_statement();
+            self.end_scope();
+            self.emit_one_byte(OpCode::None);
+            self.matches(Comma);
+        } else {
+            self.parse_expression();
+            if !self.check(RightBrace) {
+                self.consume(Comma, "Expect ',' after match arm.");
+            }
+        }
+
+        // The arm's value replaces the matched value, above which only the
+        // bindings are left to pop, here and on the way to the next arm
+        self.emit_two_bytes(SetLocal, subject);
+        self.emit_one_byte(Pop);
+        let captured = self.locals()[locals..]
+            .iter()
+            .rev()
+            .map(|local| local.is_captured)
+            .collect::<Vec<bool>>();
+        for &is_captured in &captured {
+            self.emit_pop_local(is_captured);
+        }
+        let end_jump = self.emit_jump(Jump);
+        if let Some(guard_jump) = guard_jump {
+            self.patch_jump(guard_jump);
+            self.emit_one_byte(Pop);
+            for &is_captured in &captured {
+                self.emit_pop_local(is_captured);
+            }
+        }
+        if let Some(fail_jump) = fail_jump {
+            let next_jump = guard_jump.map(|_| self.emit_jump(Jump));
+            self.patch_jump(fail_jump);
+            self.emit_one_byte(Pop);
+            if let Some(next_jump) = next_jump {
+                self.patch_jump(next_jump);
+            }
+        }
+        self.locals().truncate(locals);
+        self.scope_depth -= 1;
+        end_jump
+    }
+
+    // Emits a test leaving whether the value at path matches, unless the
+    // pattern always matches, and returns whether it did
+    fn parse_pattern(&mut self, path: &[Access], bindings: &mut Vec<(Token, Vec<Access>)>) -> bool {
+        let binding_count = bindings.len();
+        let tested = self.parse_single_pattern(path, bindings);
+        if !self.check(TokenKind::Pipe) {
+            return tested;
+        }
+
+        if !tested {
+            self.emit_one_byte(OpCode::True);
+        }
+        let mut end_jumps = Vec::new();
+        while self.matches(TokenKind::Pipe) {
+            let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+            end_jumps.push(self.emit_jump(OpCode::Jump));
+            self.patch_jump(else_jump);
+            self.emit_one_byte(OpCode::Pop);
+            if !self.parse_single_pattern(path, bindings) {
+                self.emit_one_byte(OpCode::True);
+            }
+        }
+        for jump in end_jumps {
+            self.patch_jump(jump);
+        }
+        if bindings.len() > binding_count {
+            self.parser()
+                .error("Can't bind variables in '|' alternatives.");
+        }
+        true
+    }
+
+    fn parse_single_pattern(
+        &mut self,
+        path: &[Access],
+        bindings: &mut Vec<(Token, Vec<Access>)>,
+    ) -> bool {
+        use TokenKind::*;
+
+        self.advance();
+        let token = self.parser().previous.clone();
+        match token.kind {
+            Identifier if token.lexeme == "_" => false,
+            Identifier if self.matches(LeftBrace) => {
+                self.parse_struct_pattern(token, path, bindings);
+                true
+            }
+            Identifier if self.matches(Dot) || self.matches(ColonColon) => {
+                self.parse_variant_pattern(token, path, bindings);
+                true
+            }
+            Identifier => {
+                bindings.push((token, path.to_vec()));
+                false
+            }
+            LeftBracket => {
+                self.parse_list_pattern(path, bindings);
+                true
+            }
+            Number | String | True | False | None | Minus => {
+                self.emit_path(path);
+                self.parse_literal_pattern();
+                self.emit_one_byte(OpCode::Equal);
+                true
+            }
+            _ => {
+                self.parser().error("Expect pattern.");
+                false
+            }
+        }
+    }
+
+    fn parse_literal_pattern(&mut self) {
+        use TokenKind::*;
+
+        let kind = self.parser().previous.kind;
+        match kind {
+            Number => self.parse_number_literal(false),
+            String => self.parser_string_literal(false),
+            Minus => {
+                self.consume(Number, "Expect number after '-'.");
+                self.parse_number_literal(false);
+                self.emit_one_byte(OpCode::Negate);
+            }
+            _ => self.parser_literal(false),
+        }
+    }
+
+    // Point { x: 0, y } tests the type, then each field with a pattern
+    fn parse_struct_pattern(
+        &mut self,
+        name: Token,
+        path: &[Access],
+        bindings: &mut Vec<(Token, Vec<Access>)>,
+    ) {
+        use TokenKind::*;
+
+        self.emit_path(path);
+        self.parse_named_variable(name.lexeme, false);
+        self.emit_one_byte(OpCode::IsInstance);
+        let mut fail_jumps = Vec::new();
+        while !self.check(RightBrace) && !self.check(EOF) {
+            self.consume(Identifier, "Expect field name.");
+            let field = self.parser().previous.clone();
+            let constant = self.emit_identifier_constant(field.lexeme.clone());
+            let mut field_path = path.to_vec();
+            field_path.push(Access::Field(constant));
+            if self.matches(Colon) {
+                fail_jumps.push(self.emit_jump(OpCode::JumpIfFalse));
+                self.emit_one_byte(OpCode::Pop);
+                if !self.parse_pattern(&field_path, bindings) {
+                    self.emit_one_byte(OpCode::True);
+                }
+            } else {
+                bindings.push((field, field_path));
+            }
+            if !self.matches(Comma) {
+                break;
+            }
+        }
+        self.consume(RightBrace, "Expect '}' after struct pattern.");
+        for jump in fail_jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    // Shape.Circle(r), or Shape::Circle(r), tests the variant, then each
+    // payload field with a pattern. Shape.Circle alone matches whatever the
+    // payload
+    fn parse_variant_pattern(
+        &mut self,
+        r#enum: Token,
+        path: &[Access],
+        bindings: &mut Vec<(Token, Vec<Access>)>,
+    ) {
+        use TokenKind::*;
+
+        self.emit_path(path);
+        self.parse_named_variable(r#enum.lexeme, false);
+        self.consume(Identifier, "Expect variant name.");
+        let name = self.parser().previous.lexeme.clone();
+        let name = self.emit_identifier_constant(name);
+        self.emit_operand(OpCode::Constant, name);
+        self.emit_one_byte(OpCode::IsVariant);
+        self.emit_two_bytes(0, 0);
+        let operands = self.current_chunk().bytecodes.len() - 2;
+        if !self.matches(LeftParen) {
+            return;
+        }
+
+        let mut count = 0;
+        let mut fail_jumps = Vec::new();
+        while !self.check(RightParen) && !self.check(EOF) {
+            let mut field_path = path.to_vec();
+            field_path.push(Access::Payload(count));
+            fail_jumps.push(self.emit_jump(OpCode::JumpIfFalse));
+            self.emit_one_byte(OpCode::Pop);
+            if !self.parse_pattern(&field_path, bindings) {
+                self.emit_one_byte(OpCode::True);
+            }
+            count += 1;
+            if !self.matches(Comma) {
+                break;
+            }
+        }
+        self.consume(RightParen, "Expect ')' after variant pattern.");
+        if count > u8::MAX.into() {
+            self.parser()
+                .error("Can't have more than 255 fields in a variant pattern.");
+        }
+        self.current_chunk().bytecodes[operands] = count as u8;
+        self.current_chunk().bytecodes[operands + 1] = 1;
+        for jump in fail_jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    // [first, 2, ..] tests the length, then each element with a pattern
+    fn parse_list_pattern(&mut self, path: &[Access], bindings: &mut Vec<(Token, Vec<Access>)>) {
+        use TokenKind::*;
+
+        self.emit_path(path);
+        self.emit_one_byte(OpCode::MatchList);
+        self.emit_two_bytes(0, 0);
+        let operands = self.current_chunk().bytecodes.len() - 2;
+        let mut len = 0;
+        let mut has_rest = false;
+        let mut fail_jumps = Vec::new();
+        while !self.check(RightBracket) && !self.check(EOF) {
+            if self.matches(DotDot) {
+                has_rest = true;
+                break;
+            }
+            let mut element_path = path.to_vec();
+            element_path.push(Access::Index(len));
+            fail_jumps.push(self.emit_jump(OpCode::JumpIfFalse));
+            self.emit_one_byte(OpCode::Pop);
+            if !self.parse_pattern(&element_path, bindings) {
+                self.emit_one_byte(OpCode::True);
+            }
+            len += 1;
+            if !self.matches(Comma) {
+                break;
+            }
+        }
+        self.consume(RightBracket, "Expect ']' after list pattern.");
+        if len > u8::MAX.into() {
+            self.parser()
+                .error("Can't have more than 255 elements in a list pattern.");
+        }
+        self.current_chunk().bytecodes[operands] = len as u8;
+        self.current_chunk().bytecodes[operands + 1] = has_rest as u8;
+        for jump in fail_jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn emit_path(&mut self, path: &[Access]) {
+        for access in path {
+            match *access {
+                Access::Local(slot) => self.emit_two_bytes(OpCode::GetLocal, slot),
+                Access::Field(name) => self.emit_operand(OpCode::GetProperty, name),
+                Access::Index(index) => {
+                    self.emit_constant(Value::Number(index as f64));
+                    self.emit_one_byte(OpCode::GetIndex);
+                }
+                Access::Payload(index) => self.emit_two_bytes(OpCode::GetPayload, index as u8),
+            }
+        }
+    }
+}
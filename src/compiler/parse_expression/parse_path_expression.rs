@@ -0,0 +1,24 @@
+use super::Compiler;
+use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+
+impl Compiler {
+    // Point::origin reads like a property, but can't be assigned to
+    pub fn parse_path_expression(&mut self, _can_assign: bool) {
+        use OpCode::*;
+        use TokenKind::*;
+
+        let start = self.expression_start;
+        self.consume(Identifier, "Expect name after '::'.");
+        let name = self.parser().previous.lexeme.clone();
+        let name = self.emit_identifier_constant(name);
+        if self.matches(LeftParen) {
+            let arg_count = self.argument_list();
+            self.emit_spanning(start, |compiler| {
+                compiler.emit_operand(Invoke, name);
+                compiler.emit_one_byte(arg_count);
+            });
+        } else {
+            self.emit_spanning(start, |compiler| compiler.emit_operand(GetProperty, name));
+        }
+    }
+}
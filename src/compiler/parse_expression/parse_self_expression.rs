@@ -6,6 +6,18 @@ impl Compiler {
             self.parser().error("Can't use 'self' outside of a class.");
             return;
         }
+        if self.in_static_function() {
+            self.parser()
+                .error("Can't use 'self' in a static function.");
+            return;
+        }
         self.parse_variable_expression(false);
     }
+
+    pub fn in_static_function(&self) -> bool {
+        self.current_class
+            .borrow()
+            .as_ref()
+            .is_some_and(|class| *class.in_static.borrow())
+    }
 }
@@ -0,0 +1,44 @@
+use super::Compiler;
+use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+
+impl Compiler {
+    pub fn parse_super_expression(&mut self, _can_assign: bool) {
+        use OpCode::*;
+        use TokenKind::*;
+
+        let has_superclass = self
+            .current_class
+            .borrow()
+            .as_ref()
+            .map(|class| *class.has_superclass.borrow());
+        match has_superclass {
+            Option::None => self
+                .parser()
+                .error("Can't use 'super' outside of a struct."),
+            Some(false) => self.parser().error_with_help(
+                "Can't use 'super' in a struct with no parent.",
+                "declare a parent with 'struct Name: Parent { ... }'",
+            ),
+            Some(true) if self.in_static_function() => self
+                .parser()
+                .error("Can't use 'super' in a static function."),
+            Some(true) => {}
+        }
+
+        self.consume(Dot, "Expect '.' after 'super'.");
+        self.consume(Identifier, "Expect parent method name.");
+        let name = self.parser().previous.lexeme.clone();
+        let name = self.emit_identifier_constant(name);
+
+        self.parse_named_variable("self".to_string(), false);
+        if self.matches(LeftParen) {
+            let arg_count = self.argument_list();
+            self.parse_named_variable("super".to_string(), false);
+            self.emit_operand(SuperInvoke, name);
+            self.emit_one_byte(arg_count);
+        } else {
+            self.parse_named_variable("super".to_string(), false);
+            self.emit_operand(GetSuper, name);
+        }
+    }
+}
@@ -9,11 +9,12 @@ impl Compiler {
         use TokenKind::*;
 
         let unary_operator = self.parser().previous.kind;
+        let start = self.parser().previous.span.start;
         self.parse_precedence(Precedence::Unary);
-        match unary_operator {
-            Bang => self.emit_one_byte(Not),
-            Minus => self.emit_one_byte(Negate),
+        self.emit_spanning(start, |compiler| match unary_operator {
+            Bang => compiler.emit_one_byte(Not),
+            Minus => compiler.emit_one_byte(Negate),
             _ => panic!("unary operator not found"),
-        }
+        });
     }
 }
@@ -4,44 +4,75 @@ use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
 impl Compiler {
     pub fn parse_variable_expression(&mut self, can_assign: bool) {
         let name = self.parser().previous.lexeme.to_string();
-        self.parse_named_variable(name, can_assign);
+        if self.allow_struct_literals && self.check(TokenKind::LeftBrace) {
+            self.parse_named_variable(name, false);
+            self.parse_struct_literal();
+        } else {
+            self.parse_named_variable(name, can_assign);
+        }
     }
 
     pub fn parse_named_variable(&mut self, name: String, can_assign: bool) {
         use OpCode::*;
 
+        let start = self.parser().previous.span.start;
         let get_opcode: OpCode;
         let set_opcode: OpCode;
-        let mut arg = self.resolve_local_variable(&name);
-        if arg.is_some() {
+        let arg;
+        if let Some(index) = self.resolve_local_variable(&name) {
+            arg = index.into();
             get_opcode = GetLocal;
             set_opcode = SetLocal;
+        } else if let Some(index) = self.resolve_upvalue(&name) {
+            arg = index.into();
+            get_opcode = GetUpvalue;
+            set_opcode = SetUpvalue;
         } else {
-            let index = self.emit_identifier_constant(name);
-            arg = Some(index);
+            arg = self.global_slot(&name);
             get_opcode = GetGlobal;
             set_opcode = SetGlobal;
         }
 
         if can_assign && self.matches(TokenKind::Equal) {
             self.parse_expression();
-            self.emit_two_bytes(set_opcode, arg.unwrap());
+            self.emit_spanning(start, |compiler| compiler.emit_variable(set_opcode, arg));
+        } else if let Some(operator) = self.match_compound_assignment(can_assign) {
+            self.emit_variable(get_opcode, arg);
+            self.parse_expression();
+            self.emit_spanning(start, |compiler| {
+                compiler.emit_one_byte(operator);
+                compiler.emit_variable(set_opcode, arg);
+            });
         } else {
-            self.emit_two_bytes(get_opcode, arg.unwrap());
+            self.emit_variable(get_opcode, arg);
         }
     }
 
-    fn resolve_local_variable(&mut self, name: &str) -> Option<u8> {
-        for (i, local) in self.locals.iter().enumerate().rev() {
-            if name == local.name.lexeme {
-                if local.depth.is_none() {
-                    self.parser()
-                        .error("Can't read local variable in its own initializer.");
-                }
-                return Some(i as u8);
+    // Locals and upvalues are always a single byte, with no wide variant
+    fn emit_variable(&mut self, opcode: OpCode, arg: usize) {
+        match opcode {
+            OpCode::GetLocal | OpCode::SetLocal | OpCode::GetUpvalue | OpCode::SetUpvalue => {
+                self.emit_two_bytes(opcode, arg as u8)
             }
+            _ => self.emit_operand(opcode, arg),
+        }
+    }
+
+    fn resolve_local_variable(&mut self, name: &str) -> Option<u8> {
+        let (index, is_initialized) = self.current_function.resolve_local(name)?;
+        if !is_initialized {
+            self.parser()
+                .error("Can't read local variable in its own initializer.");
         }
+        Some(index)
+    }
 
-        None
+    fn resolve_upvalue(&mut self, name: &str) -> Option<u8> {
+        let index = self.current_function.resolve_upvalue(name)?;
+        if self.current_function.upvalues.borrow().len() > u8::MAX.into() {
+            self.parser()
+                .error("Too many closure variables in function.");
+        }
+        Some(index)
     }
 }
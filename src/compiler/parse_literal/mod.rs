@@ -1,8 +1,11 @@
 use super::Compiler;
 use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
 
+mod parse_list_literal;
+mod parse_map_literal;
 mod parse_number_literal;
 mod parse_string_literal;
+mod parse_struct_literal;
 
 impl Compiler {
     pub fn parser_literal(&mut self, _can_assign: bool) {
@@ -0,0 +1,26 @@
+use super::Compiler;
+use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+
+impl Compiler {
+    pub fn parse_list_literal(&mut self, _can_assign: bool) {
+        use TokenKind::*;
+
+        let mut item_count: u8 = 0;
+        if !self.check(RightBracket) {
+            loop {
+                self.parse_nested_expression();
+                if item_count == u8::MAX {
+                    self.parser()
+                        .error("Can't have more than 255 items in a list literal.");
+                }
+                item_count = item_count.wrapping_add(1);
+                if !self.matches(Comma) || self.check(RightBracket) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(RightBracket, "Expect ']' after list items.");
+        self.emit_two_bytes(OpCode::BuildList, item_count);
+    }
+}
@@ -0,0 +1,28 @@
+use super::Compiler;
+use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+
+impl Compiler {
+    pub fn parse_map_literal(&mut self, _can_assign: bool) {
+        use TokenKind::*;
+
+        let mut entry_count: u8 = 0;
+        if !self.check(RightBrace) {
+            loop {
+                self.parse_nested_expression();
+                self.consume(Colon, "Expect ':' after map key.");
+                self.parse_nested_expression();
+                if entry_count == u8::MAX {
+                    self.parser()
+                        .error("Can't have more than 255 entries in a map literal.");
+                }
+                entry_count = entry_count.wrapping_add(1);
+                if !self.matches(Comma) || self.check(RightBrace) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(RightBrace, "Expect '}' after map entries.");
+        self.emit_two_bytes(OpCode::BuildMap, entry_count);
+    }
+}
@@ -1,10 +1,46 @@
 use super::Compiler;
-use crate::value::Value;
+use crate::{
+    chunk::opcode::OpCode,
+    scanner::{string_literal::string_value, token::TokenKind},
+    value::Value,
+};
 
 impl Compiler {
     pub fn parser_string_literal(&mut self, _can_assign: bool) {
-        let end = self.parser().previous.lexeme.len() - 1;
-        let string = self.parser().previous.lexeme[1..end].to_string();
-        self.emit_constant(Value::String(string));
+        let string = string_value(&self.parser().previous.lexeme);
+        self.emit_constant(Value::String(self.intern(&string)));
+    }
+
+    // "a ${b} c" scans as Interpolation("a ${), b, String(} c")
+    pub fn parse_interpolation(&mut self, _can_assign: bool) {
+        let head = string_value(&self.parser().previous.lexeme);
+        let mut has_value = !head.is_empty();
+        if has_value {
+            self.emit_constant(Value::String(self.intern(&head)));
+        }
+
+        loop {
+            self.parse_expression();
+            self.emit_one_byte(OpCode::Stringify);
+            if has_value {
+                self.emit_one_byte(OpCode::Add);
+            }
+            has_value = true;
+
+            let is_segment = self.matches(TokenKind::Interpolation);
+            if !is_segment && !self.matches(TokenKind::String) {
+                self.parser()
+                    .error_at_current("Expect end of string interpolation.");
+                return;
+            }
+            let segment = string_value(&self.parser().previous.lexeme);
+            if !segment.is_empty() {
+                self.emit_constant(Value::String(self.intern(&segment)));
+                self.emit_one_byte(OpCode::Add);
+            }
+            if !is_segment {
+                break;
+            }
+        }
     }
 }
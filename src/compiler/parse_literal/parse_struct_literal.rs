@@ -0,0 +1,41 @@
+use super::Compiler;
+use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+
+impl Compiler {
+    // Point { x: 1, y } builds an instance directly, without calling new
+    pub fn parse_struct_literal(&mut self) {
+        use TokenKind::*;
+
+        let start = self.parser().previous.span.start;
+        self.consume(LeftBrace, "Expect '{' before struct literal fields.");
+        let mut fields = Vec::new();
+        while !self.check(RightBrace) && !self.check(EOF) {
+            self.consume(Identifier, "Expect field name.");
+            let name = self.parser().previous.lexeme.clone();
+            if fields.contains(&name) {
+                self.parser()
+                    .error("Already a field with this name in this literal.");
+            }
+            let constant = self.emit_identifier_constant(name.clone());
+            self.emit_operand(OpCode::Constant, constant);
+            if self.matches(Colon) {
+                self.parse_nested_expression();
+            } else {
+                self.parse_named_variable(name.clone(), false);
+            }
+            fields.push(name);
+            if fields.len() > u8::MAX.into() {
+                self.parser()
+                    .error("Can't have more than 255 fields in a struct literal.");
+            }
+            if !self.matches(Comma) {
+                break;
+            }
+        }
+
+        self.consume(RightBrace, "Expect '}' after struct literal fields.");
+        self.emit_spanning(start, |compiler| {
+            compiler.emit_two_bytes(OpCode::BuildInstance, fields.len() as u8)
+        });
+    }
+}
@@ -5,17 +5,22 @@ mod parse_block_statement;
 mod parse_break_statement;
 mod parse_continue_statement;
 mod parse_expression_statement;
+mod parse_for_in_statement;
 mod parse_for_statement;
 mod parse_if_statement;
 mod parse_loop_statement;
 mod parse_print_statement;
 mod parse_return_statement;
+mod parse_try_statement;
 mod parse_while_statement;
 
 impl Compiler {
     pub fn parse_statement(&mut self) {
         use TokenKind::*;
 
+        let height = self.locals().len();
+        self.mark_stack_height(height);
+
         if self.matches(Print) {
             self.parse_print_statement();
         } else if self.matches(For) {
@@ -36,6 +41,10 @@ impl Compiler {
             self.parse_loop_statement();
         } else if self.matches(Continue) {
             self.parse_continue_statement();
+        } else if self.matches(Try) {
+            self.parse_try_statement();
+        } else if self.matches(Throw) {
+            self.parse_throw_statement();
         } else {
             self.parse_expression_statement();
         }
@@ -1,4 +1,7 @@
-use crate::{chunk::opcode::OpCode, compiler::Compiler, scanner::token::TokenKind};
+use crate::{
+    compiler::{Compiler, LoopExit},
+    scanner::token::TokenKind,
+};
 
 impl Compiler {
     pub fn parse_break_statement(&mut self) {
@@ -6,6 +9,6 @@ impl Compiler {
             self.parser().error("Cannot use 'break' outside of a loop.");
         }
         self.consume(TokenKind::Semicolon, "Expect ';' after 'break'.");
-        self.emit_jump(OpCode::End);
+        self.emit_loop_exit(LoopExit::Break);
     }
 }
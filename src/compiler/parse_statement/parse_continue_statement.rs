@@ -1,4 +1,7 @@
-use crate::{compiler::Compiler, scanner::token::TokenKind};
+use crate::{
+    compiler::{Compiler, LoopExit},
+    scanner::token::TokenKind,
+};
 
 impl Compiler {
     pub fn parse_continue_statement(&mut self) {
@@ -8,6 +11,8 @@ impl Compiler {
         }
 
         self.consume(TokenKind::Semicolon, "Expect ';' after continue.");
-        self.emit_loop(self.loop_start.unwrap());
+        if self.loop_start.is_some() {
+            self.emit_loop_exit(LoopExit::Continue);
+        }
     }
 }
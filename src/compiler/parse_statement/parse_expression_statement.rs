@@ -3,8 +3,17 @@ use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
 
 impl Compiler {
     pub fn parse_expression_statement(&mut self) {
+        // Like a block, a match ending the statement needs no semicolon
+        let is_match = self.check(TokenKind::Match);
         self.parse_expression();
-        self.consume(TokenKind::Semicolon, "Expect ';' after expression.");
+        if is_match && self.parser().previous.kind == TokenKind::RightBrace {
+            self.matches(TokenKind::Semicolon);
+        } else {
+            self.consume_statement_end("Expect ';' after expression.");
+        }
+        if self.returns_last_value && self.scope_depth == 0 {
+            self.last_value_pop = Some(self.current_chunk().bytecodes.len());
+        }
         self.emit_one_byte(OpCode::Pop);
     }
 }
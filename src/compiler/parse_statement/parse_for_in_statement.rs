@@ -0,0 +1,68 @@
+use super::Compiler;
+use crate::{chunk::opcode::OpCode, scanner::token::TokenKind, value::Value};
+
+impl Compiler {
+    // for x in xs { A } keeps the iterator and a cursor in hidden locals
+    //
+    //          xs  Iter  Constant 0
+    // next:    ForIter iterator done  A  Loop next
+    // done:
+    pub fn parse_for_in_statement(&mut self) {
+        use TokenKind::*;
+
+        self.begin_scope();
+        let mut names = Vec::new();
+        if self.matches(LeftParen) {
+            self.consume(Identifier, "Expect variable name.");
+            names.push(self.parser().previous.clone());
+            self.consume(Comma, "Expect ',' between loop variables.");
+            self.consume(Identifier, "Expect variable name.");
+            names.push(self.parser().previous.clone());
+            self.consume(RightParen, "Expect ')' after loop variables.");
+        } else {
+            self.consume(Identifier, "Expect variable name.");
+            names.push(self.parser().previous.clone());
+        }
+        self.consume(In, "Expect 'in' after loop variable.");
+
+        let start = self.parser().current.span.start;
+        self.parse_expression_before_block();
+        self.emit_spanning(start, |compiler| compiler.emit_one_byte(OpCode::Iter));
+        let slot = self.locals().len() as u8;
+        self.add_hidden_local();
+        self.emit_constant(Value::Number(0.0));
+        self.add_hidden_local();
+
+        let prev_loop_start = self.loop_start;
+        let prev_loop_depth = self.loop_depth;
+        let prev_loop_handler_depth = self.loop_handler_depth;
+        self.loop_start = Some(self.current_chunk().bytecodes.len());
+        self.loop_depth = self.scope_depth;
+        self.loop_handler_depth = self.handler_depth;
+
+        let var_count = names.len() as u8;
+        self.emit_spanning(start, |compiler| {
+            compiler.emit_one_byte(OpCode::ForIter);
+            compiler.emit_two_bytes(slot, var_count);
+            compiler.emit_two_bytes(u8::MAX, u8::MAX);
+        });
+        let exit_jump = self.current_chunk().bytecodes.len() - 2;
+
+        self.begin_scope();
+        for name in names {
+            self.declare_local(name);
+            self.mark_initialized();
+        }
+        self.parse_statement();
+        self.end_scope();
+        self.emit_loop(self.loop_start.unwrap());
+        self.patch_jump(exit_jump);
+
+        self.end_loop();
+        self.loop_start = prev_loop_start;
+        self.loop_depth = prev_loop_depth;
+        self.loop_handler_depth = prev_loop_handler_depth;
+
+        self.end_scope();
+    }
+}
@@ -6,6 +6,14 @@ impl Compiler {
         use OpCode::*;
         use TokenKind::*;
 
+        if self.check(Identifier)
+            || self.check(LeftParen)
+                && self.scanner().peek_token(0).kind == Identifier
+                && self.scanner().peek_token(1).kind == Comma
+        {
+            return self.parse_for_in_statement();
+        }
+
         self.begin_scope();
         self.consume(LeftParen, "Expect '(' after 'for'.");
 
@@ -18,8 +26,10 @@ impl Compiler {
 
         let prev_loop_start = self.loop_start;
         let prev_loop_depth = self.loop_depth;
+        let prev_loop_handler_depth = self.loop_handler_depth;
         self.loop_start = Some(self.current_chunk().bytecodes.len()); // start condition
         self.loop_depth = self.scope_depth;
+        self.loop_handler_depth = self.handler_depth;
 
         let mut exit_jump = Option::None;
         if !self.matches(Semicolon) {
@@ -44,15 +54,17 @@ impl Compiler {
 
         self.parse_statement();
         self.emit_loop(self.loop_start.unwrap());
-        self.end_loop();
-        self.loop_start = prev_loop_start;
-        self.loop_depth = prev_loop_depth;
 
-        if exit_jump.is_some() {
-            self.patch_jump(exit_jump.unwrap());
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump);
             self.emit_one_byte(Pop);
         }
 
+        self.end_loop();
+        self.loop_start = prev_loop_start;
+        self.loop_depth = prev_loop_depth;
+        self.loop_handler_depth = prev_loop_handler_depth;
+
         self.end_scope();
     }
 }
@@ -1,10 +1,10 @@
 use super::Compiler;
-use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+use crate::chunk::opcode::OpCode;
 
 impl Compiler {
     pub fn parse_print_statement(&mut self) {
         self.parse_expression();
-        self.consume(TokenKind::Semicolon, "Expect ';' after value.");
+        self.consume_statement_end("Expect ';' after value.");
         self.emit_one_byte(OpCode::Print);
     }
 }
@@ -0,0 +1,147 @@
+use super::Compiler;
+use crate::{
+    chunk::opcode::OpCode,
+    compiler::{LoopExit, TryExits},
+    scanner::token::TokenKind,
+    value::Value,
+};
+
+impl Compiler {
+    // try { A } catch (e) { B } finally { C } compiles to
+    //
+    //          PushHandler catch   A  PopHandler  None False  Jump finally
+    // catch:   PushHandler rethrow B  PopHandler  None False  Jump finally
+    // rethrow:                                    True
+    // finally:                     C  EndFinally break continue  Jump end
+    // break:   the break again, now outside the try statement
+    // continue: the continue again
+    // end:
+    //
+    // so every way out of A and B reaches C with two hidden locals: the
+    // pending value and whether to carry on (false), rethrow it (true),
+    // return it (none, pushed by a return inside A or B) or leave the loop
+    // (0 for a break, 1 for a continue).
+    pub fn parse_try_statement(&mut self) {
+        use OpCode::*;
+        use TokenKind::*;
+
+        let slots = self.locals().len() as u8;
+        self.try_exits.push(TryExits {
+            slots: slots as usize,
+            jumps: Vec::new(),
+        });
+        let (handler, mut finally_jumps) = self.emit_handler(slots);
+        self.consume(LeftBrace, "Expect '{' after 'try'.");
+        self.parse_handled_block();
+        self.emit_two_bytes(OpCode::None, OpCode::False);
+        finally_jumps.push(self.emit_jump(Jump));
+
+        self.patch_jump(handler);
+        if self.matches(Catch) {
+            self.begin_scope();
+            self.consume(LeftParen, "Expect '(' after 'catch'.");
+            self.consume(Identifier, "Expect error variable name.");
+            let name = self.parser().previous.clone();
+            self.declare_local(name);
+            self.mark_initialized();
+            self.consume(RightParen, "Expect ')' after error variable.");
+
+            let (rethrow, rethrow_finally) = self.emit_handler(slots);
+            finally_jumps.extend(rethrow_finally);
+            self.consume(LeftBrace, "Expect '{' before catch block.");
+            self.parse_handled_block();
+            self.end_scope();
+            self.emit_two_bytes(OpCode::None, OpCode::False);
+            finally_jumps.push(self.emit_jump(Jump));
+            self.patch_jump(rethrow);
+        } else if !self.check(Finally) {
+            self.parser()
+                .error_at_current("Expect 'catch' or 'finally' after try block.");
+        }
+        self.emit_one_byte(OpCode::True);
+        let exits = self.try_exits.pop().unwrap().jumps;
+        for jump in finally_jumps
+            .into_iter()
+            .chain(exits.iter().map(|(_, jump)| *jump))
+        {
+            self.patch_jump(jump);
+        }
+
+        self.begin_scope();
+        self.add_hidden_local();
+        self.add_hidden_local();
+        if self.matches(Finally) {
+            self.consume(LeftBrace, "Expect '{' after 'finally'.");
+            self.begin_scope();
+            self.parse_block_statement();
+            self.end_scope();
+        }
+        self.emit_one_byte(EndFinally);
+        self.emit_two_bytes(0, 0);
+        self.emit_two_bytes(0, 0);
+        let targets = self.current_chunk().bytecodes.len() - 4;
+        let len = self.locals().len();
+        self.locals().truncate(len - 2);
+        self.scope_depth -= 1;
+
+        if exits.is_empty() {
+            return;
+        }
+        let end_jump = self.emit_jump(Jump);
+        for (i, exit) in [LoopExit::Break, LoopExit::Continue]
+            .into_iter()
+            .enumerate()
+        {
+            if exits.iter().any(|(kind, _)| *kind == exit) {
+                let offset = self.current_chunk().bytecodes.len() - targets - 4;
+                self.current_chunk().bytecodes[targets + i * 2] = (offset >> 8) as u8;
+                self.current_chunk().bytecodes[targets + i * 2 + 1] = offset as u8;
+                self.emit_loop_exit(exit);
+            }
+        }
+        self.patch_jump(end_jump);
+    }
+
+    // Leaves the innermost try statement for its finally block, which then
+    // repeats the break or continue from outside the statement
+    pub fn exit_through_finally(&mut self, exit: LoopExit) {
+        let slots = self.try_exits.last().unwrap().slots;
+        let captured = self.locals()[slots..]
+            .iter()
+            .rev()
+            .map(|local| local.is_captured)
+            .collect::<Vec<bool>>();
+        for is_captured in captured {
+            self.emit_pop_local(is_captured);
+        }
+        self.emit_two_bytes(OpCode::PopHandler, OpCode::None);
+        self.emit_constant(Value::Number(exit as u8 as f64));
+        let jump = self.emit_jump(OpCode::Jump);
+        self.try_exits.last_mut().unwrap().jumps.push((exit, jump));
+    }
+
+    pub fn parse_throw_statement(&mut self) {
+        let start = self.parser().previous.span.start;
+        self.parse_expression();
+        self.consume_statement_end("Expect ';' after thrown value.");
+        self.emit_spanning(start, |compiler| compiler.emit_one_byte(OpCode::Throw));
+    }
+
+    // Emits the catch and finally jump offsets, then how many stack slots
+    // of the frame survive unwinding to the handler
+    fn emit_handler(&mut self, slots: u8) -> (usize, Vec<usize>) {
+        let catch = self.emit_jump(OpCode::PushHandler);
+        self.emit_two_bytes(u8::MAX, u8::MAX);
+        self.emit_one_byte(slots);
+        self.handler_depth += 1;
+        (catch, vec![catch + 2])
+    }
+
+    fn parse_handled_block(&mut self) {
+        self.begin_scope();
+        self.parse_block_statement();
+        self.end_scope();
+        self.emit_one_byte(OpCode::PopHandler);
+        self.handler_depth -= 1;
+    }
+}
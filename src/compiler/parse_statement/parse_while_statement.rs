@@ -1,5 +1,5 @@
 use super::Compiler;
-use crate::{chunk::opcode::OpCode, scanner::token::TokenKind};
+use crate::{chunk::opcode::OpCode, compiler::LoopExit, scanner::token::TokenKind};
 
 impl Compiler {
     pub fn parse_while_statement(&mut self) {
@@ -8,8 +8,10 @@ impl Compiler {
 
         let prev_loop_start = self.loop_start;
         let prev_loop_depth = self.loop_depth;
+        let prev_loop_handler_depth = self.loop_handler_depth;
         self.loop_start = Some(self.current_chunk().bytecodes.len());
         self.loop_depth = self.scope_depth;
+        self.loop_handler_depth = self.handler_depth;
 
         self.consume(LeftParen, "Expect '(' after 'while'.");
         self.parse_expression();
@@ -25,17 +27,50 @@ impl Compiler {
         self.end_loop();
         self.loop_start = prev_loop_start;
         self.loop_depth = prev_loop_depth;
+        self.loop_handler_depth = prev_loop_handler_depth;
     }
 
     pub fn end_loop(&mut self) {
+        use OpCode::*;
+
         let mut offset = self.loop_start.unwrap();
         while offset < self.current_chunk().bytecodes.len() {
-            if self.current_chunk().bytecodes[offset] == OpCode::End.into() {
-                self.current_chunk().bytecodes[offset] = OpCode::Jump.into();
-                self.patch_jump(offset + 1);
-            } else {
-                offset += OpCode::from(self.current_chunk().bytecodes[offset]).to_offset() + 1;
+            match OpCode::from(self.current_chunk().bytecodes[offset]) {
+                End => {
+                    self.current_chunk().bytecodes[offset] = Jump.into();
+                    self.patch_jump(offset + 1);
+                }
+                _ => offset += self.current_chunk().instruction_len(offset),
+            }
+        }
+    }
+
+    // A break or continue inside a try statement of the loop goes through
+    // its finally block first
+    pub fn emit_loop_exit(&mut self, exit: LoopExit) {
+        if self.handler_depth > self.loop_handler_depth {
+            self.exit_through_finally(exit);
+            return;
+        }
+        self.discard_loop_locals();
+        match exit {
+            LoopExit::Break => {
+                self.emit_jump(OpCode::End);
             }
+            LoopExit::Continue => self.emit_loop(self.loop_start.unwrap()),
+        }
+    }
+
+    pub fn discard_loop_locals(&mut self) {
+        let locals = self
+            .locals()
+            .iter()
+            .rev()
+            .take_while(|local| local.depth.is_none_or(|depth| depth > self.loop_depth))
+            .map(|local| local.is_captured)
+            .collect::<Vec<bool>>();
+        for is_captured in locals {
+            self.emit_pop_local(is_captured);
         }
     }
 
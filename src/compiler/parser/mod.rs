@@ -1,13 +1,17 @@
-use crate::scanner::token::{Token, TokenKind};
-use std::cell::Cell;
+use super::CompileDiagnostic;
+use crate::{
+    diagnostic::{Diagnostic, Span},
+    scanner::token::{Token, TokenKind},
+};
+use std::cell::{Cell, RefCell};
 
 pub mod parse_rule;
 
 pub struct Parser {
     pub current: Token,
     pub previous: Token,
-    pub had_error: Cell<bool>,
     pub is_panic_mode: Cell<bool>,
+    pub diagnostics: RefCell<Vec<CompileDiagnostic>>,
 }
 
 impl Parser {
@@ -17,14 +21,18 @@ impl Parser {
                 kind: TokenKind::EOF,
                 lexeme: "".to_string(),
                 line_number: 0,
+                column: 0,
+                span: Span::default(),
             },
             previous: Token {
                 kind: TokenKind::EOF,
                 lexeme: "".to_string(),
                 line_number: 0,
+                column: 0,
+                span: Span::default(),
             },
-            had_error: Cell::new(false),
             is_panic_mode: Cell::new(false),
+            diagnostics: RefCell::new(Vec::new()),
         }
     }
 
@@ -36,25 +44,31 @@ impl Parser {
         self.error_at(&self.current, message);
     }
 
+    pub fn error_with_help(&self, message: &str, help: &str) {
+        let diagnostic = Diagnostic::error(self.previous.span, message).with_help(help);
+        self.report(&self.previous, diagnostic);
+    }
+
     pub fn error_at(&self, token: &Token, message: &str) {
-        use TokenKind::*;
+        self.report(token, Diagnostic::error(token.span, message));
+    }
+
+    pub fn had_error(&self) -> bool {
+        !self.diagnostics.borrow().is_empty()
+    }
 
+    // Errors after the first in a statement are usually knock-on effects,
+    // so they're dropped until the compiler synchronizes
+    fn report(&self, token: &Token, diagnostic: Diagnostic) {
         if self.is_panic_mode.get() {
             return;
         }
 
         self.is_panic_mode.set(true);
-
-        eprint!("[line {}] Error", token.line_number);
-
-        if token.kind == EOF {
-            eprint!(" at end");
-        } else if token.kind == Error {
-        } else {
-            eprint!(" at '{}'", token.lexeme);
-        }
-
-        eprintln!(": {message}");
-        self.had_error.set(true);
+        self.diagnostics.borrow_mut().push(CompileDiagnostic {
+            diagnostic,
+            line_number: token.line_number,
+            column: token.column,
+        });
     }
 }
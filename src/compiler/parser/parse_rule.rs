@@ -10,6 +10,7 @@ pub enum Precedence {
     And,        // and
     Equality,   // == !=
     Comparison, // < > <= >=
+    Range,      // .. ..=
     Term,       // + -
     Factor,     // * /
     Unary,      // ! -
@@ -26,18 +27,19 @@ impl From<u8> for Precedence {
             3 => Precedence::And,
             4 => Precedence::Equality,
             5 => Precedence::Comparison,
-            6 => Precedence::Term,
-            7 => Precedence::Factor,
-            8 => Precedence::Unary,
-            9 => Precedence::Call,
+            6 => Precedence::Range,
+            7 => Precedence::Term,
+            8 => Precedence::Factor,
+            9 => Precedence::Unary,
+            10 => Precedence::Call,
             _ => Precedence::Primary,
         }
     }
 }
 
-impl Into<u8> for Precedence {
-    fn into(self) -> u8 {
-        self as u8
+impl From<Precedence> for u8 {
+    fn from(value: Precedence) -> Self {
+        value as u8
     }
 }
 
@@ -80,16 +82,44 @@ impl Rules {
             ),
             (
                 TokenKind::LeftBrace,
-                ParseRule::new(None, None, Precedence::None),
+                ParseRule::new(
+                    Some(|c, can_assign| c.parse_map_literal(can_assign)),
+                    None,
+                    Precedence::None,
+                ),
             ),
             (
                 TokenKind::RightBrace,
                 ParseRule::new(None, None, Precedence::None),
             ),
+            (
+                TokenKind::LeftBracket,
+                ParseRule::new(
+                    Some(|c, can_assign| c.parse_list_literal(can_assign)),
+                    Some(|c, can_assign| c.parse_index_expression(can_assign)),
+                    Precedence::Call,
+                ),
+            ),
+            (
+                TokenKind::RightBracket,
+                ParseRule::new(None, None, Precedence::None),
+            ),
             (
                 TokenKind::Comma,
                 ParseRule::new(None, None, Precedence::None),
             ),
+            (
+                TokenKind::Colon,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::ColonColon,
+                ParseRule::new(
+                    None,
+                    Some(|c, can_assign| c.parse_path_expression(can_assign)),
+                    Precedence::Call,
+                ),
+            ),
             (
                 TokenKind::Dot,
                 ParseRule::new(
@@ -98,6 +128,22 @@ impl Rules {
                     Precedence::Call,
                 ),
             ),
+            (
+                TokenKind::DotDot,
+                ParseRule::new(
+                    None,
+                    Some(|c, can_assign| c.parse_binary_expression(can_assign)),
+                    Precedence::Range,
+                ),
+            ),
+            (
+                TokenKind::DotDotEqual,
+                ParseRule::new(
+                    None,
+                    Some(|c, can_assign| c.parse_binary_expression(can_assign)),
+                    Precedence::Range,
+                ),
+            ),
             (
                 TokenKind::Minus,
                 ParseRule::new(
@@ -218,6 +264,14 @@ impl Rules {
                     Precedence::None,
                 ),
             ),
+            (
+                TokenKind::Interpolation,
+                ParseRule::new(
+                    Some(|c, can_assign| c.parse_interpolation(can_assign)),
+                    None,
+                    Precedence::None,
+                ),
+            ),
             (
                 TokenKind::String,
                 ParseRule::new(
@@ -246,10 +300,18 @@ impl Rules {
                 TokenKind::Struct,
                 ParseRule::new(None, None, Precedence::None),
             ),
+            (
+                TokenKind::Static,
+                ParseRule::new(None, None, Precedence::None),
+            ),
             (
                 TokenKind::Else,
                 ParseRule::new(None, None, Precedence::None),
             ),
+            (
+                TokenKind::Enum,
+                ParseRule::new(None, None, Precedence::None),
+            ),
             (
                 TokenKind::False,
                 ParseRule::new(
@@ -258,9 +320,86 @@ impl Rules {
                     Precedence::None,
                 ),
             ),
+            (TokenKind::As, ParseRule::new(None, None, Precedence::None)),
+            (
+                TokenKind::Break,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::Catch,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::Continue,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::Loop,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::Finally,
+                ParseRule::new(None, None, Precedence::None),
+            ),
             (TokenKind::For, ParseRule::new(None, None, Precedence::None)),
-            (TokenKind::Fn, ParseRule::new(None, None, Precedence::None)),
+            (
+                TokenKind::Fn,
+                ParseRule::new(
+                    Some(|c, can_assign| c.parse_fn_expression(can_assign)),
+                    None,
+                    Precedence::None,
+                ),
+            ),
+            (
+                TokenKind::Pipe,
+                ParseRule::new(
+                    Some(|c, can_assign| c.parse_closure_expression(can_assign)),
+                    None,
+                    Precedence::None,
+                ),
+            ),
             (TokenKind::If, ParseRule::new(None, None, Precedence::None)),
+            (
+                TokenKind::Import,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (TokenKind::In, ParseRule::new(None, None, Precedence::None)),
+            (
+                TokenKind::Match,
+                ParseRule::new(
+                    Some(|c, can_assign| c.parse_match_expression(can_assign)),
+                    None,
+                    Precedence::None,
+                ),
+            ),
+            (
+                TokenKind::FatArrow,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::MinusEqual,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::PlusEqual,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::StarEqual,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::SlashEqual,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::ModuloEqual,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (
+                TokenKind::PowerEqual,
+                ParseRule::new(None, None, Precedence::None),
+            ),
             (
                 TokenKind::None,
                 ParseRule::new(
@@ -293,6 +432,14 @@ impl Rules {
                     Precedence::None,
                 ),
             ),
+            (
+                TokenKind::Super,
+                ParseRule::new(
+                    Some(|c, can_assign| c.parse_super_expression(can_assign)),
+                    None,
+                    Precedence::None,
+                ),
+            ),
             (
                 TokenKind::True,
                 ParseRule::new(
@@ -301,6 +448,11 @@ impl Rules {
                     Precedence::None,
                 ),
             ),
+            (
+                TokenKind::Throw,
+                ParseRule::new(None, None, Precedence::None),
+            ),
+            (TokenKind::Try, ParseRule::new(None, None, Precedence::None)),
             (TokenKind::Let, ParseRule::new(None, None, Precedence::None)),
             (
                 TokenKind::While,
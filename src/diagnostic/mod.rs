@@ -0,0 +1,117 @@
+use std::fmt::{Display, Write};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+// Byte range into the source text
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    // 1-based line and column of the start of the span
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        let start = self.start.min(source.len());
+        let before = source.get(..start).unwrap_or_default();
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        (line, column)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+    pub help: Option<String>,
+    pub note: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, span: Span, message: &str) -> Self {
+        Self {
+            severity,
+            span,
+            message: message.to_string(),
+            help: None,
+            note: None,
+        }
+    }
+
+    pub fn error(span: Span, message: &str) -> Self {
+        Self::new(Severity::Error, span, message)
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.note = Some(note.to_string());
+        self
+    }
+
+    // Render the message with the offending source line underlined:
+    //
+    //   error: Expect ';' after value.
+    //    --> 1:8
+    //     |
+    //   1 | print 1
+    //     |        ^
+    pub fn render(&self, source: &str) -> String {
+        let (line, column) = self.span.location(source);
+        let text = source.lines().nth(line - 1).unwrap_or_default();
+        let underline = source
+            .get(self.span.start..self.span.end)
+            .map_or(0, |spanned| {
+                spanned.lines().next().unwrap_or_default().chars().count()
+            })
+            .clamp(1, (text.chars().count() + 1).saturating_sub(column).max(1));
+        let gutter = " ".repeat(line.to_string().len());
+
+        let mut output = String::new();
+        let _ = writeln!(output, "{}: {}", self.severity, self.message);
+        let _ = writeln!(output, "{gutter}--> {line}:{column}");
+        let _ = writeln!(output, "{gutter} |");
+        let _ = writeln!(output, "{line} | {text}");
+        let _ = writeln!(
+            output,
+            "{gutter} | {}{}",
+            " ".repeat(column - 1),
+            "^".repeat(underline)
+        );
+        if let Some(help) = &self.help {
+            let _ = writeln!(output, "{gutter} = help: {help}");
+        }
+        if let Some(note) = &self.note {
+            let _ = writeln!(output, "{gutter} = note: {note}");
+        }
+        output
+    }
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+#[test]
+fn test_location() {
+    let source = "let a = 1;\nprint a + b;";
+    assert_eq!((1, 1), Span::new(0, 3).location(source));
+    assert_eq!((2, 11), Span::new(21, 22).location(source));
+    assert_eq!((2, 13), Span::new(23, 23).location(source));
+}
+
+#[test]
+fn test_location_counts_characters() {
+    let source = "let é = \"ü\" + 1;";
+    assert_eq!((1, 13), Span::new(14, 15).location(source));
+}
+
+#[test]
+fn test_render() {
+    let source = "let a = 1;\nprint a + true;";
+    let diagnostic = Diagnostic::error(Span::new(17, 25), "Operands must be numbers.");
+    assert_eq!(
+        "error: Operands must be numbers.\n \
+         --> 2:7\n  \
+         |\n\
+         2 | print a + true;\n  \
+         |       ^^^^^^^^\n",
+        diagnostic.render(source)
+    );
+}
+
+#[test]
+fn test_render_at_end() {
+    let source = "print 1";
+    let diagnostic =
+        Diagnostic::error(Span::new(7, 7), "Expect ';' after value.").with_help("add a ';' here");
+    assert_eq!(
+        "error: Expect ';' after value.\n \
+         --> 1:8\n  \
+         |\n\
+         1 | print 1\n  \
+         |        ^\n  \
+         = help: add a ';' here\n",
+        diagnostic.render(source)
+    );
+}
+
+#[test]
+fn test_render_multiline_span() {
+    let source = "print \"a\n b\" + 1;";
+    let diagnostic = Diagnostic::error(
+        Span::new(6, 16),
+        "Operands must be two numbers or two strings.",
+    )
+    .with_note("the span continues past this line");
+    let rendered = diagnostic.render(source);
+    assert!(rendered.contains("1 | print \"a\n  |       ^^\n"));
+    assert!(rendered.ends_with("  = note: the span continues past this line\n"));
+}
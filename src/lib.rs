@@ -0,0 +1,18 @@
+mod chunk;
+mod compiler;
+pub mod diagnostic;
+pub(crate) mod object;
+mod scanner;
+pub(crate) mod value;
+pub(crate) mod vm;
+
+pub use compiler::{CompileDiagnostic, InterpretError};
+pub use diagnostic::{Diagnostic, Severity, Span};
+pub use object::native_function_object::{Arity, NativeFunctionObject};
+pub use scanner::is_complete;
+pub use value::Value;
+pub use vm::{
+    heap::HeapStats,
+    runtime_error::{RuntimeError, TraceFrame},
+    VirtualMachine,
+};
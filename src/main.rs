@@ -1,17 +1,13 @@
+use rustscript::{is_complete, InterpretError, Value, VirtualMachine};
+use rustyline::{error::ReadlineError, DefaultEditor};
 use std::{
-    env::args,
-    fs,
-    io::{self, Write},
+    env::{self, args},
+    fs, io,
+    path::PathBuf,
     process::exit,
 };
 
-use vm::VirtualMachine;
-mod chunk;
-mod compiler;
-mod object;
-mod scanner;
-mod value;
-mod vm;
+const HISTORY_FILE: &str = ".rustscript_history";
 
 fn main() {
     let args: Vec<String> = args().collect();
@@ -20,8 +16,10 @@ fn main() {
     match args.len() {
         1 => repl(&mut vm),
         2 => {
-            run_file(&mut vm, &args[1]).expect(&format!("Could not open file {}", &args[1]));
-            exit(74);
+            if let Err(error) = run_file(&mut vm, &args[1]) {
+                eprintln!("Could not open file {}: {}", &args[1], error);
+                exit(74);
+            }
         }
         _ => {
             println!("Usage: rust_script [script]");
@@ -31,34 +29,82 @@ fn main() {
 }
 
 fn repl(vm: &mut VirtualMachine) {
-    println!("RustScript REPL (Ctrl+C to exit)");
-    loop {
-        print!("> ");
-        io::stdout().flush().expect("Failed to flush stdout");
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            eprintln!("Could not start the REPL: {}", error);
+            exit(74);
+        }
+    };
+    let history = history_path();
+    let _ = editor.load_history(&history);
 
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
+    println!("RustScript REPL (Ctrl+D to exit)");
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() { "> " } else { "... " };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+                if !is_complete(&input) {
+                    continue;
+                }
+                let source = input.trim_end();
+                if !source.is_empty() {
+                    let _ = editor.add_history_entry(source);
+                    evaluate(vm, source);
+                }
+                input.clear();
+            }
+            Err(ReadlineError::Interrupted) => input.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                eprintln!("{}", error);
+                break;
+            }
+        }
+    }
+    let _ = editor.save_history(&history);
+}
 
-        // Pass the input to your compiler or interpreter
-        let result = vm.interpret(&input);
+// Errors are reported and the session carries on
+fn evaluate(vm: &mut VirtualMachine, source: &str) {
+    match vm.eval(source) {
+        Ok(Value::None) => {}
+        Ok(value) => println!("{:?}", value),
+        Err(error) => report(&error, source),
+    }
+}
 
-        match result {
-            Ok(_) => {}
-            Err(compiler::InterpretError::CompileError) => exit(65),
-            Err(compiler::InterpretError::RuntimeError) => exit(70),
-        }
+fn report(error: &InterpretError, source: &str) {
+    match error {
+        InterpretError::CompileError(diagnostics) => diagnostics
+            .iter()
+            .for_each(|diagnostic| eprint!("{}", diagnostic.render(source))),
+        InterpretError::RuntimeError(error) => eprint!("{}", error.render()),
     }
 }
 
+fn history_path() -> PathBuf {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(HISTORY_FILE)
+}
+
 fn run_file(vm: &mut VirtualMachine, path: &str) -> io::Result<()> {
     let file = fs::read_to_string(path)?;
-    let result = vm.interpret(&file);
+    let result = vm.interpret_with_path(&file, path);
 
     match result {
         Ok(()) => Ok(()),
-        Err(compiler::InterpretError::CompileError) => exit(65),
-        Err(compiler::InterpretError::RuntimeError) => exit(70),
+        Err(error) => {
+            report(&error, &file);
+            match error {
+                InterpretError::CompileError(_) => exit(65),
+                InterpretError::RuntimeError(_) => exit(70),
+            }
+        }
     }
 }
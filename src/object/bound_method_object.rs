@@ -1,14 +1,14 @@
-use super::function_object::FunctionObject;
+use super::closure_object::ClosureObject;
 use crate::value::Value;
 use std::{fmt::Display, rc::Rc};
 
 pub struct BoundMethodObject {
     pub receiver: Value,
-    pub method: Rc<FunctionObject>,
+    pub method: Rc<ClosureObject>,
 }
 
 impl BoundMethodObject {
-    pub fn new(receiver: Value, method: Rc<FunctionObject>) -> Self {
+    pub fn new(receiver: Value, method: Rc<ClosureObject>) -> Self {
         Self { receiver, method }
     }
 }
@@ -0,0 +1,27 @@
+use super::{
+    function_object::FunctionObject, module_object::ModuleObject, upvalue_object::UpvalueObject,
+};
+use std::{fmt::Display, rc::Rc};
+
+pub struct ClosureObject {
+    pub function: Rc<FunctionObject>,
+    pub upvalues: Vec<Rc<UpvalueObject>>,
+    pub module: Option<Rc<ModuleObject>>,
+}
+
+impl ClosureObject {
+    pub fn new(function: Rc<FunctionObject>) -> Self {
+        let upvalues = Vec::with_capacity(function.upvalue_count);
+        Self {
+            function,
+            upvalues,
+            module: None,
+        }
+    }
+}
+
+impl Display for ClosureObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.function)
+    }
+}
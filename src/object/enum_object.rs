@@ -0,0 +1,29 @@
+use std::{fmt::Display, rc::Rc};
+
+pub struct EnumVariant {
+    pub name: Rc<str>,
+    pub fields: Vec<Rc<str>>,
+}
+
+pub struct EnumObject {
+    pub name: String,
+    pub variants: Vec<EnumVariant>,
+}
+
+impl EnumObject {
+    pub fn new(name: String, variants: Vec<EnumVariant>) -> Self {
+        Self { name, variants }
+    }
+
+    pub fn find_variant(&self, name: &str) -> Option<usize> {
+        self.variants
+            .iter()
+            .position(|variant| &*variant.name == name)
+    }
+}
+
+impl Display for EnumObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
@@ -5,7 +5,7 @@ pub struct FunctionObject {
     pub arity: usize,
     pub chunk: Chunk,
     pub name: String,
-    // pub upvalue_count: usize,
+    pub upvalue_count: usize,
 }
 
 impl FunctionObject {
@@ -14,11 +14,17 @@ impl FunctionObject {
             arity: 0,
             chunk: Chunk::new(),
             name: "".to_string(),
-            // upvalue_count,
+            upvalue_count: 0,
         }
     }
 }
 
+impl Default for FunctionObject {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display for FunctionObject {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.name.is_empty() {
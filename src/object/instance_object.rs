@@ -4,14 +4,21 @@ use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
 
 pub struct InstanceObject {
     pub r#struct: Rc<StructObject>,
-    pub fields: RefCell<HashMap<String, Value>>,
+    pub fields: RefCell<HashMap<Rc<str>, Value>>,
 }
 
 impl InstanceObject {
+    // Declared fields start as none, until the VM evaluates their defaults
     pub fn new(r#struct: Rc<StructObject>) -> Self {
+        let fields = r#struct
+            .fields
+            .borrow()
+            .iter()
+            .map(|field| (field.name.clone(), Value::None))
+            .collect();
         Self {
             r#struct: Rc::clone(&r#struct),
-            fields: RefCell::new(HashMap::new()),
+            fields: RefCell::new(fields),
         }
     }
 }
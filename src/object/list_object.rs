@@ -0,0 +1,38 @@
+use crate::value::{cycle, Value};
+use std::{cell::RefCell, fmt::Display};
+
+pub struct ListObject {
+    pub items: RefCell<Vec<Value>>,
+}
+
+impl ListObject {
+    pub fn new(items: Vec<Value>) -> Self {
+        Self {
+            items: RefCell::new(items),
+        }
+    }
+
+    pub fn resolve_index(&self, index: f64) -> Option<usize> {
+        let len = self.items.borrow().len() as f64;
+        let index = if index < 0.0 { len + index } else { index };
+        if index.fract() != 0.0 || index < 0.0 || index >= len {
+            return None;
+        }
+        Some(index as usize)
+    }
+}
+
+impl Display for ListObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        cycle::display(self, f, "[...]", |f| {
+            write!(f, "[")?;
+            for (i, item) in self.items.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", item)?;
+            }
+            write!(f, "]")
+        })
+    }
+}
@@ -0,0 +1,53 @@
+use crate::value::{cycle, map_key::MapKey, Value};
+use std::{cell::RefCell, collections::HashMap, fmt::Display};
+
+pub struct MapObject {
+    pub entries: RefCell<HashMap<MapKey, Value>>,
+    pub keys: RefCell<Vec<MapKey>>,
+}
+
+impl MapObject {
+    pub fn new() -> Self {
+        Self {
+            entries: RefCell::new(HashMap::new()),
+            keys: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<Value> {
+        self.entries.borrow().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: MapKey, value: Value) {
+        if self
+            .entries
+            .borrow_mut()
+            .insert(key.clone(), value)
+            .is_none()
+        {
+            self.keys.borrow_mut().push(key);
+        }
+    }
+}
+
+impl Default for MapObject {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for MapObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        cycle::display(self, f, "{...}", |f| {
+            let entries = self.entries.borrow();
+            write!(f, "{{")?;
+            for (i, key) in self.keys.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: {}", key, entries[key])?;
+            }
+            write!(f, "}}")
+        })
+    }
+}
@@ -1,5 +1,13 @@
 pub mod bound_method_object;
+pub mod closure_object;
+pub mod enum_object;
 pub mod function_object;
 pub mod instance_object;
+pub mod list_object;
+pub mod map_object;
+pub mod module_object;
 pub mod native_function_object;
+pub mod range_object;
 pub mod struct_object;
+pub mod upvalue_object;
+pub mod variant_object;
@@ -0,0 +1,28 @@
+use crate::{value::Value, vm::globals::Globals};
+use std::{cell::RefCell, fmt::Display, path::PathBuf, rc::Rc};
+
+pub struct ModuleObject {
+    pub name: String,
+    pub path: PathBuf,
+    pub globals: Rc<RefCell<Globals>>,
+}
+
+impl ModuleObject {
+    pub fn new(name: String, path: PathBuf) -> Self {
+        Self {
+            name,
+            path,
+            globals: Rc::new(RefCell::new(Globals::new())),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().get_by_name(name)
+    }
+}
+
+impl Display for ModuleObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
@@ -1,18 +1,91 @@
-use std::time::SystemTime;
+use std::{fmt::Display, rc::Rc, time::SystemTime};
 
-use crate::value::Value;
+use super::list_object::ListObject;
+use crate::{value::Value, vm::runtime_error::RuntimeError};
+
+#[derive(Clone, Copy)]
+pub enum Arity {
+    Exact(usize),
+    Range(usize, usize),
+    Variadic(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, arg_count: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => arg_count == n,
+            Arity::Range(min, max) => min <= arg_count && arg_count <= max,
+            Arity::Variadic(min) => min <= arg_count,
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n),
+            Arity::Range(min, max) => write!(f, "{} to {}", min, max),
+            Arity::Variadic(min) => write!(f, "at least {}", min),
+        }
+    }
+}
 
 pub trait NativeFunctionObject {
-    fn call(&self, arg_count: usize, args: &[Value]) -> Value;
+    fn name(&self) -> &str;
+    fn arity(&self) -> Arity;
+    fn call(&self, arg_count: usize, args: &[Value]) -> Result<Value, RuntimeError>;
+}
+
+type NativeFn = dyn Fn(&[Value]) -> Result<Value, RuntimeError>;
+
+pub struct NativeClosure {
+    name: String,
+    arity: Arity,
+    function: Box<NativeFn>,
+}
+
+impl NativeClosure {
+    pub fn new<F>(name: &str, arity: Arity, function: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value, RuntimeError> + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            arity,
+            function: Box::new(function),
+        }
+    }
+}
+
+impl NativeFunctionObject for NativeClosure {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn call(&self, _arg_count: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+        (self.function)(args)
+    }
 }
 
 pub struct Clock {}
 
 impl NativeFunctionObject for Clock {
-    fn call(&self, _arg_count: usize, _args: &[Value]) -> Value {
+    fn name(&self) -> &str {
+        "clock"
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Exact(0)
+    }
+
+    fn call(&self, _arg_count: usize, _args: &[Value]) -> Result<Value, RuntimeError> {
         match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-            Ok(n) => Value::Number(n.as_millis() as f64),
-            Err(_) => panic!("can't get system time"),
+            Ok(n) => Ok(Value::Number(n.as_millis() as f64)),
+            Err(_) => Err(RuntimeError::new("Can't get system time.")),
         }
     }
 }
@@ -20,34 +93,61 @@ impl NativeFunctionObject for Clock {
 pub struct Println {}
 
 impl NativeFunctionObject for Println {
-    fn call(&self, _arg_count: usize, args: &[Value]) -> Value {
+    fn name(&self) -> &str {
+        "println"
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Variadic(1)
+    }
+
+    fn call(&self, _arg_count: usize, args: &[Value]) -> Result<Value, RuntimeError> {
         if let Value::String(format) = &args[0] {
-            let mut format = format.clone();
-            args.iter()
-                .skip(1)
-                .for_each(|value| format = format.replacen("{}", &value.to_string(), 1));
-            println!("{}", format);
+            let mut values = args.iter().skip(1);
+            let mut pieces = format.split("{}");
+            let mut output = pieces.next().unwrap_or_default().to_string();
+            for piece in pieces {
+                match values.next() {
+                    Some(value) => output.push_str(&value.to_string()),
+                    Option::None => output.push_str("{}"),
+                }
+                output.push_str(piece);
+            }
+            println!("{}", output);
         } else {
-            panic!("println must have a format string.");
+            return Err(RuntimeError::new("println must have a format string."));
         }
 
-        Value::None
+        Ok(Value::None)
     }
 }
 
 pub struct ConvertToNumber {}
 
 impl NativeFunctionObject for ConvertToNumber {
-    fn call(&self, _arg_count: usize, args: &[Value]) -> Value {
+    fn name(&self) -> &str {
+        "Number"
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Exact(1)
+    }
+
+    fn call(&self, _arg_count: usize, args: &[Value]) -> Result<Value, RuntimeError> {
         use Value::*;
         match &args[0] {
-            Number(a) => Number(*a),
-            String(n) => {
-                let number = n.parse::<f64>().expect("can convert this string to number");
-                Number(number)
-            }
-            None => Number(0.0),
-            _ => panic!("can not convert object to number"),
+            Number(a) => Ok(Number(*a)),
+            String(n) => match n.trim().parse::<f64>() {
+                Ok(number) => Ok(Number(number)),
+                Err(_) => Err(RuntimeError::new(&format!(
+                    "Can't convert '{}' to a number.",
+                    n
+                ))),
+            },
+            value => Err(RuntimeError::new(&format!(
+                "Can't convert {} to a number.",
+                value
+            ))),
         }
     }
 }
@@ -55,13 +155,37 @@ impl NativeFunctionObject for ConvertToNumber {
 pub struct ConvertToString {}
 
 impl NativeFunctionObject for ConvertToString {
-    fn call(&self, _arg_count: usize, args: &[Value]) -> Value {
-        use Value::*;
+    fn name(&self) -> &str {
+        "String"
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Exact(1)
+    }
+
+    fn call(&self, _arg_count: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+        Ok(Value::String(args[0].to_string().into()))
+    }
+}
+
+pub struct Keys {}
+
+impl NativeFunctionObject for Keys {
+    fn name(&self) -> &str {
+        "keys"
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Exact(1)
+    }
+
+    fn call(&self, _arg_count: usize, args: &[Value]) -> Result<Value, RuntimeError> {
         match &args[0] {
-            Number(a) => String(a.to_string()),
-            String(n) => String(n.to_string()),
-            None => String("none".to_string()),
-            _ => panic!("can not convert object to string"),
+            Value::Map(map) => {
+                let keys = map.keys.borrow().iter().map(|key| key.to_value()).collect();
+                Ok(Value::List(Rc::new(ListObject::new(keys))))
+            }
+            _ => Err(RuntimeError::new("keys() expects a map.")),
         }
     }
 }
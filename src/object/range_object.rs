@@ -0,0 +1,36 @@
+use std::fmt::Display;
+
+#[derive(PartialEq)]
+pub struct RangeObject {
+    pub start: f64,
+    pub end: f64,
+    pub inclusive: bool,
+}
+
+impl RangeObject {
+    pub fn new(start: f64, end: f64, inclusive: bool) -> Self {
+        Self {
+            start,
+            end,
+            inclusive,
+        }
+    }
+
+    // The nth number of the range, counting from its start
+    pub fn nth(&self, n: usize) -> Option<f64> {
+        let number = self.start + n as f64;
+        let in_range = if self.inclusive {
+            number <= self.end
+        } else {
+            number < self.end
+        };
+        in_range.then_some(number)
+    }
+}
+
+impl Display for RangeObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operator = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{operator}{}", self.start, self.end)
+    }
+}
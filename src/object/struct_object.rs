@@ -1,10 +1,22 @@
-use super::function_object::FunctionObject;
+use super::closure_object::ClosureObject;
 use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
 
+// A declared field, and the function giving the value new instances start
+// with. Defaults are evaluated for each instance, so none is shared. A field
+// without a default starts as none but must be given in a struct literal
+#[derive(Clone)]
+pub struct StructField {
+    pub name: Rc<str>,
+    pub default: Option<Rc<ClosureObject>>,
+}
+
 pub struct StructObject {
     pub name: String,
-    pub methods: RefCell<HashMap<String, Rc<FunctionObject>>>,
-    pub init: RefCell<Option<Rc<FunctionObject>>>,
+    pub methods: RefCell<HashMap<Rc<str>, Rc<ClosureObject>>>,
+    pub statics: RefCell<HashMap<Rc<str>, Rc<ClosureObject>>>,
+    // Fields declared by this struct and its parents, parents first
+    pub fields: RefCell<Vec<StructField>>,
+    pub parent: RefCell<Option<Rc<StructObject>>>,
 }
 
 impl StructObject {
@@ -12,8 +24,46 @@ impl StructObject {
         Self {
             name,
             methods: RefCell::new(HashMap::new()),
-            init: RefCell::new(None),
+            statics: RefCell::new(HashMap::new()),
+            fields: RefCell::new(Vec::new()),
+            parent: RefCell::new(None),
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Rc<ClosureObject>> {
+        if let Some(method) = self.methods.borrow().get(name) {
+            return Some(method.clone());
+        }
+        self.parent.borrow().as_ref()?.find_method(name)
+    }
+
+    pub fn find_static(&self, name: &str) -> Option<Rc<ClosureObject>> {
+        if let Some(function) = self.statics.borrow().get(name) {
+            return Some(function.clone());
         }
+        self.parent.borrow().as_ref()?.find_static(name)
+    }
+
+    // Takes on the parent's fields, ahead of the ones declared afterwards
+    pub fn inherit(&self, parent: Rc<StructObject>) {
+        *self.fields.borrow_mut() = parent.fields.borrow().clone();
+        self.parent.replace(Some(parent));
+    }
+
+    // Only structs declaring their fields reject setting any other field
+    pub fn accepts_field(&self, name: &str) -> bool {
+        let fields = self.fields.borrow();
+        fields.is_empty() || fields.iter().any(|field| &*field.name == name)
+    }
+
+    // Whether this struct is other or inherits from it
+    pub fn is_a(&self, other: &StructObject) -> bool {
+        std::ptr::eq(self, other)
+            || self
+                .parent
+                .borrow()
+                .as_ref()
+                .is_some_and(|parent| parent.is_a(other))
     }
 }
 
@@ -0,0 +1,20 @@
+use crate::value::Value;
+use std::cell::RefCell;
+
+pub struct UpvalueObject {
+    pub location: usize,
+    pub closed: RefCell<Option<Value>>,
+}
+
+impl UpvalueObject {
+    pub fn new(location: usize) -> Self {
+        Self {
+            location,
+            closed: RefCell::new(None),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.closed.borrow().is_none()
+    }
+}
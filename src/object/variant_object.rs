@@ -0,0 +1,57 @@
+use super::enum_object::{EnumObject, EnumVariant};
+use crate::value::Value;
+use std::{fmt::Display, rc::Rc};
+
+pub struct VariantObject {
+    pub r#enum: Rc<EnumObject>,
+    pub index: usize,
+    pub payload: Vec<Value>,
+}
+
+impl VariantObject {
+    pub fn new(r#enum: Rc<EnumObject>, index: usize, payload: Vec<Value>) -> Self {
+        Self {
+            r#enum,
+            index,
+            payload,
+        }
+    }
+
+    pub fn variant(&self) -> &EnumVariant {
+        &self.r#enum.variants[self.index]
+    }
+
+    pub fn field(&self, name: &str) -> Option<Value> {
+        let index = self
+            .variant()
+            .fields
+            .iter()
+            .position(|field| &**field == name)?;
+        Some(self.payload[index].clone())
+    }
+}
+
+impl PartialEq for VariantObject {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.r#enum, &other.r#enum)
+            && self.index == other.index
+            && self.payload == other.payload
+    }
+}
+
+impl Display for VariantObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.r#enum, self.variant().name)?;
+        if self.payload.is_empty() {
+            return Ok(());
+        }
+        write!(f, "(")?;
+        for (i, value) in self.payload.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        write!(f, ")")
+    }
+}
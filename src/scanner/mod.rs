@@ -1,23 +1,56 @@
+use crate::diagnostic::Span;
 use crate::scanner::token::Token;
 use crate::scanner::token::TokenKind;
 use crate::scanner::token::TokenKind::*;
+use string_literal::unescape;
 
 #[cfg(test)]
 mod tests;
 
+pub mod string_literal;
 pub mod token;
 
+// Whether the source closes every bracket and string it opens, so a REPL
+// can ask for more lines instead of reporting a compile error
+pub fn is_complete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source);
+    let mut depth = 0;
+    loop {
+        let token = scanner.scan_token();
+        match token.kind {
+            LeftParen | LeftBrace | LeftBracket => depth += 1,
+            RightParen | RightBrace | RightBracket => depth -= 1,
+            Interpolation if !token.lexeme.starts_with('}') => depth += 1,
+            String if token.lexeme.starts_with('}') => depth -= 1,
+            Error if token.lexeme.starts_with("Unterminated") => return false,
+            EOF => return depth <= 0,
+            _ => {}
+        }
+    }
+}
+
 pub struct Scanner {
     source: Vec<char>,
     start: usize,
     current: usize,
     line_number: usize,
+    // Byte offset of each character, so spans index the original &str
+    offsets: Vec<usize>,
+    line_start: usize,
+    start_column: usize,
+    // Brace depth inside each unfinished "${...}"
+    interpolations: Vec<usize>,
 }
 
 // Implementation for Scanner
 impl Scanner {
     // Initialize a new Scanner
     pub fn new(source: &str) -> Self {
+        let mut offsets = source
+            .char_indices()
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+        offsets.push(source.len());
         let mut source = source.chars().collect::<Vec<char>>();
         source.push('\0');
         Scanner {
@@ -25,39 +58,132 @@ impl Scanner {
             start: 0,
             current: 0,
             line_number: 1,
+            offsets,
+            line_start: 0,
+            start_column: 1,
+            interpolations: Vec::new(),
         }
     }
 
+    // Scans ahead without consuming anything; distance 0 is the token the
+    // next scan_token call returns
+    pub fn peek_token(&mut self, distance: usize) -> Token {
+        let (start, current, line_number) = (self.start, self.current, self.line_number);
+        let (line_start, start_column) = (self.line_start, self.start_column);
+        let interpolations = self.interpolations.clone();
+        let mut token = self.scan_token();
+        for _ in 0..distance {
+            token = self.scan_token();
+        }
+        (self.start, self.current, self.line_number) = (start, current, line_number);
+        (self.line_start, self.start_column) = (line_start, start_column);
+        self.interpolations = interpolations;
+        token
+    }
+
     pub fn scan_token(&mut self) -> Token {
         self.skip_whitespace();
         self.start = self.current;
+        self.start_column = self.start - self.line_start + 1;
 
         if self.is_at_end() {
             return self.make_token(EOF);
         }
 
         let c = self.advance();
+        if c == 'r' && self.is_raw_string_start() {
+            return self.raw_string();
+        }
         if c.is_alphabetic() || c == '_' {
             return self.identifier();
         }
-        if c.is_digit(10) {
+        if c.is_ascii_digit() {
             return self.number();
         }
 
         match c {
             '(' => self.make_token(LeftParen),
             ')' => self.make_token(RightParen),
-            '{' => self.make_token(LeftBrace),
-            '}' => self.make_token(RightBrace),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.make_token(LeftBrace)
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string()
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.make_token(RightBrace)
+                }
+                Option::None => self.make_token(RightBrace),
+            },
+            '[' => self.make_token(LeftBracket),
+            ']' => self.make_token(RightBracket),
             ';' => self.make_token(Semicolon),
             ',' => self.make_token(Comma),
-            '.' => self.make_token(Dot),
-            '-' => self.make_token(Minus),
-            '+' => self.make_token(Plus),
-            '*' => self.make_token(Star),
-            '/' => self.make_token(Slash),
-            '%' => self.make_token(Modulo),
-            '^' => self.make_token(Power),
+            '|' => self.make_token(Pipe),
+            ':' => {
+                if self.matches(':') {
+                    self.make_token(ColonColon)
+                } else {
+                    self.make_token(Colon)
+                }
+            }
+            '.' => {
+                if !self.matches('.') {
+                    self.make_token(Dot)
+                } else if self.matches('=') {
+                    self.make_token(DotDotEqual)
+                } else {
+                    self.make_token(DotDot)
+                }
+            }
+            '-' => {
+                if self.matches('=') {
+                    self.make_token(MinusEqual)
+                } else {
+                    self.make_token(Minus)
+                }
+            }
+            '+' => {
+                if self.matches('=') {
+                    self.make_token(PlusEqual)
+                } else {
+                    self.make_token(Plus)
+                }
+            }
+            '*' => {
+                if self.matches('=') {
+                    self.make_token(StarEqual)
+                } else {
+                    self.make_token(Star)
+                }
+            }
+            '/' => {
+                if self.matches('=') {
+                    self.make_token(SlashEqual)
+                } else {
+                    self.make_token(Slash)
+                }
+            }
+            '%' => {
+                if self.matches('=') {
+                    self.make_token(ModuloEqual)
+                } else {
+                    self.make_token(Modulo)
+                }
+            }
+            '^' => {
+                if self.matches('=') {
+                    self.make_token(PowerEqual)
+                } else {
+                    self.make_token(Power)
+                }
+            }
             '!' => {
                 if self.matches('=') {
                     self.make_token(BangEqual)
@@ -68,6 +194,8 @@ impl Scanner {
             '=' => {
                 if self.matches('=') {
                     self.make_token(EqualEqual)
+                } else if self.matches('>') {
+                    self.make_token(FatArrow)
                 } else {
                     self.make_token(Equal)
                 }
@@ -117,9 +245,7 @@ impl Scanner {
     }
 
     fn matches(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            false
-        } else if self.peek() != expected {
+        if self.is_at_end() || self.peek() != expected {
             false
         } else {
             self.current += 1;
@@ -133,6 +259,8 @@ impl Scanner {
             kind,
             lexeme: self.source[self.start..self.current].iter().collect(),
             line_number: self.line_number,
+            column: self.start_column,
+            span: self.span(),
         }
     }
 
@@ -142,15 +270,42 @@ impl Scanner {
             kind: Error,
             lexeme: message.to_owned(),
             line_number: self.line_number,
+            column: self.start_column,
+            span: self.span(),
         }
     }
 
+    // An error token for the characters start..end inside the current
+    // token, which may sit lines below where the token began
+    fn error_token_between(&self, message: &str, start: usize, end: usize) -> Token {
+        let later_lines = self.source[start..self.current]
+            .iter()
+            .filter(|&&c| c == '\n')
+            .count();
+        let line_start = self.source[..start]
+            .iter()
+            .rposition(|&c| c == '\n')
+            .map_or(0, |i| i + 1);
+        Token {
+            kind: Error,
+            lexeme: message.to_owned(),
+            line_number: self.line_number - later_lines,
+            column: start - line_start + 1,
+            span: Span::new(self.offsets[start], self.offsets[end]),
+        }
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.offsets[self.start], self.offsets[self.current])
+    }
+
     fn skip_whitespace(&mut self) {
         loop {
             let c = self.peek();
             if c == '\n' {
                 self.line_number += 1;
                 self.advance();
+                self.line_start = self.current;
                 continue;
             }
             if c.is_whitespace() {
@@ -185,14 +340,45 @@ impl Scanner {
 
     fn identifier_type(&self) -> TokenKind {
         match self.source.get(self.start).unwrap() {
-            'a' => self.check_keyword(1, 2, "nd", And),
+            'a' => {
+                if self.current - self.start > 1 {
+                    match self.source.get(self.start + 1).unwrap() {
+                        'n' => self.check_keyword(2, 1, "d", And),
+                        's' => self.check_keyword(2, 0, "", As),
+                        _ => Identifier,
+                    }
+                } else {
+                    Identifier
+                }
+            }
             'b' => self.check_keyword(1, 4, "reak", Break),
-            'c' => self.check_keyword(1, 7, "ontinue", Continue),
-            'e' => self.check_keyword(1, 3, "lse", Else),
+            'c' => {
+                if self.current - self.start > 1 {
+                    match self.source.get(self.start + 1).unwrap() {
+                        'a' => self.check_keyword(2, 3, "tch", Catch),
+                        'o' => self.check_keyword(2, 6, "ntinue", Continue),
+                        _ => Identifier,
+                    }
+                } else {
+                    Identifier
+                }
+            }
+            'e' => {
+                if self.current - self.start > 1 {
+                    match self.source.get(self.start + 1).unwrap() {
+                        'l' => self.check_keyword(2, 2, "se", Else),
+                        'n' => self.check_keyword(2, 2, "um", Enum),
+                        _ => Identifier,
+                    }
+                } else {
+                    Identifier
+                }
+            }
             'f' => {
                 if self.current - self.start > 1 {
                     match self.source.get(self.start + 1).unwrap() {
                         'a' => self.check_keyword(2, 3, "lse", False),
+                        'i' => self.check_keyword(2, 5, "nally", Finally),
                         'o' => self.check_keyword(2, 1, "r", For),
                         'n' => self.check_keyword(2, 0, "", Fn),
                         _ => Identifier,
@@ -201,7 +387,18 @@ impl Scanner {
                     Identifier
                 }
             }
-            'i' => self.check_keyword(1, 1, "f", If),
+            'i' => {
+                if self.current - self.start > 1 {
+                    match self.source.get(self.start + 1).unwrap() {
+                        'f' => self.check_keyword(2, 0, "", If),
+                        'm' => self.check_keyword(2, 4, "port", Import),
+                        'n' => self.check_keyword(2, 0, "", In),
+                        _ => Identifier,
+                    }
+                } else {
+                    Identifier
+                }
+            }
             'l' => {
                 if self.current - self.start > 1 {
                     match self.source.get(self.start + 1).unwrap() {
@@ -213,6 +410,7 @@ impl Scanner {
                     Identifier
                 }
             }
+            'm' => self.check_keyword(1, 4, "atch", Match),
             'n' => self.check_keyword(1, 3, "one", None),
             'o' => self.check_keyword(1, 1, "r", Or),
             'p' => self.check_keyword(1, 4, "rint", Print),
@@ -221,33 +419,51 @@ impl Scanner {
                 if self.current - self.start > 1 {
                     match self.source.get(self.start + 1).unwrap() {
                         'e' => self.check_keyword(2, 2, "lf", Self_),
-                        't' => self.check_keyword(2, 4, "ruct", Struct),
+                        't' if self.current - self.start > 2 => {
+                            match self.source.get(self.start + 2).unwrap() {
+                                'a' => self.check_keyword(3, 3, "tic", Static),
+                                'r' => self.check_keyword(3, 3, "uct", Struct),
+                                _ => Identifier,
+                            }
+                        }
+                        'u' => self.check_keyword(2, 3, "per", Super),
+                        _ => Identifier,
+                    }
+                } else {
+                    Identifier
+                }
+            }
+            't' => {
+                if self.current - self.start > 2 {
+                    match self.source[self.start + 1..self.start + 3] {
+                        ['h', 'r'] => self.check_keyword(3, 2, "ow", Throw),
+                        ['r', 'u'] => self.check_keyword(3, 1, "e", True),
+                        ['r', 'y'] => self.check_keyword(3, 0, "", Try),
                         _ => Identifier,
                     }
                 } else {
                     Identifier
                 }
             }
-            't' => self.check_keyword(1, 3, "rue", True),
             'w' => self.check_keyword(1, 4, "hile", While),
             _ => Identifier,
         }
     }
 
     fn identifier(&mut self) -> Token {
-        while self.peek().is_alphabetic() || self.peek().is_digit(10) || self.peek() == '_' {
+        while self.peek().is_alphabetic() || self.peek().is_ascii_digit() || self.peek() == '_' {
             self.advance();
         }
         self.make_token(self.identifier_type())
     }
 
     fn number(&mut self) -> Token {
-        while self.peek().is_digit(10) {
+        while self.peek().is_ascii_digit() {
             self.advance();
         }
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance();
-            while self.peek().is_digit(10) {
+            while self.peek().is_ascii_digit() {
                 self.advance();
             }
         }
@@ -255,9 +471,21 @@ impl Scanner {
     }
 
     fn string(&mut self) -> Token {
+        let mut kind = String;
         while self.peek() != '"' && !self.is_at_end() {
+            if self.peek() == '$' && self.peek_next() == '{' {
+                kind = Interpolation;
+                break;
+            }
+            if self.peek() == '\\' {
+                self.advance();
+                if self.is_at_end() {
+                    break;
+                }
+            }
             if self.peek() == '\n' {
                 self.line_number += 1;
+                self.line_start = self.current + 1;
             }
             self.advance();
         }
@@ -265,7 +493,53 @@ impl Scanner {
             return self.error_token("Unterminated string.");
         }
         self.advance();
-        self.make_token(String)
+        if kind == Interpolation {
+            self.advance();
+            self.interpolations.push(0);
+        }
+
+        let end = if kind == Interpolation { 2 } else { 1 };
+        let body: std::string::String = self.source[self.start + 1..self.current - end]
+            .iter()
+            .collect();
+        if let Err(error) = unescape(&body) {
+            let start = self.start + 1 + body[..error.start].chars().count();
+            let end = start + body[error.start..error.end].chars().count();
+            return self.error_token_between(&error.message, start, end);
+        }
+        self.make_token(kind)
+    }
+
+    // r"..." or r#"..."#, with any number of '#'
+    fn is_raw_string_start(&self) -> bool {
+        let hashes = self.source[self.current..]
+            .iter()
+            .take_while(|&&c| c == '#')
+            .count();
+        self.source.get(self.current + hashes) == Some(&'"')
+    }
+
+    fn raw_string(&mut self) -> Token {
+        let mut hashes = 0;
+        while self.matches('#') {
+            hashes += 1;
+        }
+        self.advance();
+        loop {
+            if self.is_at_end() {
+                return self.error_token("Unterminated raw string.");
+            }
+            let c = self.advance();
+            if c == '\n' {
+                self.line_number += 1;
+                self.line_start = self.current;
+            }
+            let is_closed = (0..hashes).all(|i| self.source.get(self.current + i) == Some(&'#'));
+            if c == '"' && is_closed {
+                self.current += hashes;
+                return self.make_token(String);
+            }
+        }
     }
 
     // Scan and tokenize the source code
@@ -0,0 +1,87 @@
+use std::str::Chars;
+
+// Where the bad escape sits, as byte offsets into the unescaped text
+pub struct EscapeError {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+// Produce the runtime value of a string or interpolation segment lexeme
+pub fn string_value(lexeme: &str) -> String {
+    if let Some(raw) = lexeme.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return raw[hashes + 1..raw.len() - hashes - 1].to_string();
+    }
+    let end = if lexeme.ends_with("${") { 2 } else { 1 };
+    unescape(&lexeme[1..lexeme.len() - end]).unwrap_or_default()
+}
+
+pub fn unescape(text: &str) -> Result<String, EscapeError> {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    let position = |chars: &Chars| text.len() - chars.as_str().len();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        let start = position(&chars) - 1;
+        let error = |message: String, chars: &Chars| EscapeError {
+            message,
+            start,
+            end: position(chars),
+        };
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('"') => '"',
+            Some('$') => '$',
+            Some('\\') => '\\',
+            Some('u') => match unicode_escape(&mut chars) {
+                Some(c) => c,
+                None => {
+                    return Err(error(
+                        "Invalid unicode escape, expected '\\u{XXXX}'.".to_string(),
+                        &chars,
+                    ));
+                }
+            },
+            // A backslash at the end of a line skips the line break and the
+            // indentation that follows it
+            Some('\n') => {
+                while chars.clone().next().is_some_and(char::is_whitespace) {
+                    chars.next();
+                }
+                continue;
+            }
+            Some(c) => {
+                return Err(error(format!("Invalid escape sequence '\\{}'.", c), &chars));
+            }
+            None => {
+                return Err(error("Unterminated escape sequence.".to_string(), &chars));
+            }
+        };
+        result.push(escaped);
+    }
+
+    Ok(result)
+}
+
+fn unicode_escape(chars: &mut Chars) -> Option<char> {
+    if chars.next()? != '{' {
+        return None;
+    }
+    let mut digits = String::new();
+    loop {
+        match chars.next()? {
+            '}' => break,
+            c if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+            _ => return None,
+        }
+    }
+    char::from_u32(u32::from_str_radix(&digits, 16).ok()?)
+}
@@ -8,7 +8,9 @@ fn test_left_paren() {
         Token {
             kind: LeftParen,
             lexeme: "(".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -22,7 +24,9 @@ fn test_right_paren() {
         Token {
             kind: RightParen,
             lexeme: ")".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -36,7 +40,9 @@ fn test_left_brace() {
         Token {
             kind: LeftBrace,
             lexeme: "{".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -50,7 +56,41 @@ fn test_right_brace() {
         Token {
             kind: RightBrace,
             lexeme: "}".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_left_bracket() {
+    let mut scanner = Scanner::new("[");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: LeftBracket,
+            lexeme: "[".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_right_bracket() {
+    let mut scanner = Scanner::new("]");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: RightBracket,
+            lexeme: "]".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -64,7 +104,25 @@ fn test_comma() {
         Token {
             kind: Comma,
             lexeme: ",".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_colon() {
+    let mut scanner = Scanner::new(":");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: Colon,
+            lexeme: ":".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -78,7 +136,9 @@ fn test_dot() {
         Token {
             kind: Dot,
             lexeme: ".".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -92,7 +152,9 @@ fn test_minus() {
         Token {
             kind: Minus,
             lexeme: "-".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -106,7 +168,9 @@ fn test_plus() {
         Token {
             kind: Plus,
             lexeme: "+".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -120,7 +184,9 @@ fn test_semicolon() {
         Token {
             kind: Semicolon,
             lexeme: ";".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -134,7 +200,9 @@ fn test_slash() {
         Token {
             kind: Slash,
             lexeme: "/".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -148,7 +216,9 @@ fn test_star() {
         Token {
             kind: Star,
             lexeme: "*".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -162,7 +232,9 @@ fn test_bang() {
         Token {
             kind: Bang,
             lexeme: "!".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -176,7 +248,9 @@ fn test_bang_equal() {
         Token {
             kind: BangEqual,
             lexeme: "!=".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 2)
         },
         token
     );
@@ -190,7 +264,9 @@ fn test_equal() {
         Token {
             kind: Equal,
             lexeme: "=".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -204,7 +280,9 @@ fn test_equal_equal() {
         Token {
             kind: EqualEqual,
             lexeme: "==".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 2)
         },
         token
     );
@@ -218,7 +296,9 @@ fn test_greater() {
         Token {
             kind: Greater,
             lexeme: ">".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -232,7 +312,9 @@ fn test_greater_equal() {
         Token {
             kind: GreaterEqual,
             lexeme: ">=".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 2)
         },
         token
     );
@@ -246,7 +328,9 @@ fn test_less() {
         Token {
             kind: Less,
             lexeme: "<".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -260,7 +344,9 @@ fn test_less_equal() {
         Token {
             kind: LessEqual,
             lexeme: "<=".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 2)
         },
         token
     );
@@ -274,7 +360,9 @@ fn test_identifier() {
         Token {
             kind: Identifier,
             lexeme: "abc123_".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 7)
         },
         token
     );
@@ -288,7 +376,133 @@ fn test_string() {
         Token {
             kind: String,
             lexeme: "\"string \"".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 9)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_escaped_string() {
+    let mut scanner = Scanner::new("\"a \\\"b\\\" \\n\"");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: String,
+            lexeme: "\"a \\\"b\\\" \\n\"".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 12)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_raw_string() {
+    let mut scanner = Scanner::new("r#\"a \"b\" \\d\"#");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: String,
+            lexeme: "r#\"a \"b\" \\d\"#".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 13)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_interpolation() {
+    let mut scanner = Scanner::new("\"a ${ {b} } c\"");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: Interpolation,
+            lexeme: "\"a ${".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 5)
+        },
+        token
+    );
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: LeftBrace,
+            lexeme: "{".to_string(),
+            line_number: 1,
+            column: 7,
+            span: Span::new(6, 7)
+        },
+        token
+    );
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: Identifier,
+            lexeme: "b".to_string(),
+            line_number: 1,
+            column: 8,
+            span: Span::new(7, 8)
+        },
+        token
+    );
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: RightBrace,
+            lexeme: "}".to_string(),
+            line_number: 1,
+            column: 9,
+            span: Span::new(8, 9)
+        },
+        token
+    );
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: String,
+            lexeme: "} c\"".to_string(),
+            line_number: 1,
+            column: 11,
+            span: Span::new(10, 14)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_invalid_escape() {
+    let mut scanner = Scanner::new("\"one\ntwo \\q\"");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: Error,
+            lexeme: "Invalid escape sequence '\\q'.".to_string(),
+            line_number: 2,
+            column: 5,
+            span: Span::new(9, 11)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_invalid_unicode_escape() {
+    let mut scanner = Scanner::new("\"\\u{110000}\"");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: Error,
+            lexeme: "Invalid unicode escape, expected '\\u{XXXX}'.".to_string(),
+            line_number: 1,
+            column: 2,
+            span: Span::new(1, 11)
         },
         token
     );
@@ -302,7 +516,9 @@ fn test_number() {
         Token {
             kind: Number,
             lexeme: "123.4".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 5)
         },
         token
     );
@@ -316,7 +532,25 @@ fn test_and() {
         Token {
             kind: And,
             lexeme: "and".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 3)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_as() {
+    let mut scanner = Scanner::new("as");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: As,
+            lexeme: "as".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 2)
         },
         token
     );
@@ -330,7 +564,25 @@ fn test_struct() {
         Token {
             kind: Struct,
             lexeme: "struct".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 6)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_super() {
+    let mut scanner = Scanner::new("super");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: Super,
+            lexeme: "super".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 5)
         },
         token
     );
@@ -344,7 +596,9 @@ fn test_else() {
         Token {
             kind: Else,
             lexeme: "else".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 4)
         },
         token
     );
@@ -358,7 +612,9 @@ fn test_for() {
         Token {
             kind: For,
             lexeme: "for".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 3)
         },
         token
     );
@@ -372,7 +628,9 @@ fn test_fn() {
         Token {
             kind: Fn,
             lexeme: "fn".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 2)
         },
         token
     );
@@ -386,7 +644,25 @@ fn test_if() {
         Token {
             kind: If,
             lexeme: "if".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 2)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_import() {
+    let mut scanner = Scanner::new("import");
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: Import,
+            lexeme: "import".to_string(),
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 6)
         },
         token
     );
@@ -400,7 +676,9 @@ fn test_none() {
         Token {
             kind: None,
             lexeme: "none".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 4)
         },
         token
     );
@@ -414,7 +692,9 @@ fn test_or() {
         Token {
             kind: Or,
             lexeme: "or".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 2)
         },
         token
     );
@@ -428,7 +708,9 @@ fn test_print() {
         Token {
             kind: Print,
             lexeme: "print".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 5)
         },
         token
     );
@@ -442,7 +724,9 @@ fn test_return() {
         Token {
             kind: Return,
             lexeme: "return".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 6)
         },
         token
     );
@@ -456,7 +740,9 @@ fn test_self() {
         Token {
             kind: Self_,
             lexeme: "self".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 4)
         },
         token
     );
@@ -470,12 +756,131 @@ fn test_true() {
         Token {
             kind: True,
             lexeme: "true".to_string(),
-            line_number: 3
+            line_number: 3,
+            column: 1,
+            span: Span::new(2, 6)
         },
         token
     );
 }
 
+#[test]
+fn test_exception_keywords() {
+    let mut scanner = Scanner::new("try catch finally throw tr");
+    for (kind, lexeme, start) in [
+        (Try, "try", 0),
+        (Catch, "catch", 4),
+        (Finally, "finally", 10),
+        (Throw, "throw", 18),
+        (Identifier, "tr", 24),
+    ] {
+        assert_eq!(
+            Token {
+                kind,
+                lexeme: lexeme.to_string(),
+                line_number: 1,
+                column: start + 1,
+                span: Span::new(start, start + lexeme.len())
+            },
+            scanner.scan_token()
+        );
+    }
+}
+
+#[test]
+fn test_ranges() {
+    let mut scanner = Scanner::new("x in 0..10 1.5..=n");
+    for (kind, lexeme, start) in [
+        (Identifier, "x", 0),
+        (In, "in", 2),
+        (Number, "0", 5),
+        (DotDot, "..", 6),
+        (Number, "10", 8),
+        (Number, "1.5", 11),
+        (DotDotEqual, "..=", 14),
+        (Identifier, "n", 17),
+    ] {
+        assert_eq!(
+            Token {
+                kind,
+                lexeme: lexeme.to_string(),
+                line_number: 1,
+                column: start + 1,
+                span: Span::new(start, start + lexeme.len())
+            },
+            scanner.scan_token()
+        );
+    }
+}
+
+#[test]
+fn test_compound_assignment_operators() {
+    let mut scanner = Scanner::new("+= -= *= /= %= ^= + -");
+    for (kind, start) in [
+        (PlusEqual, 0),
+        (MinusEqual, 3),
+        (StarEqual, 6),
+        (SlashEqual, 9),
+        (ModuloEqual, 12),
+        (PowerEqual, 15),
+    ] {
+        let token = scanner.scan_token();
+        assert_eq!(
+            (kind, Span::new(start, start + 2)),
+            (token.kind, token.span)
+        );
+    }
+    assert_eq!(Plus, scanner.scan_token().kind);
+    assert_eq!(Minus, scanner.scan_token().kind);
+}
+
+#[test]
+fn test_match_tokens() {
+    let mut scanner = Scanner::new("match x { 1 | _ => y }");
+    let kinds = std::iter::from_fn(|| Some(scanner.scan_token().kind))
+        .take_while(|kind| *kind != EOF)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            Match, Identifier, LeftBrace, Number, Pipe, Identifier, FatArrow, Identifier,
+            RightBrace
+        ],
+        kinds
+    );
+}
+
+#[test]
+fn test_enum_keyword() {
+    let mut scanner = Scanner::new("enum else en");
+    assert_eq!(Enum, scanner.scan_token().kind);
+    assert_eq!(Else, scanner.scan_token().kind);
+    assert_eq!(Identifier, scanner.scan_token().kind);
+}
+
+#[test]
+fn test_static_paths() {
+    let mut scanner = Scanner::new("static struct stat Point::origin a: b");
+    let kinds = std::iter::from_fn(|| Some(scanner.scan_token().kind))
+        .take_while(|kind| *kind != EOF)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            Static, Struct, Identifier, Identifier, ColonColon, Identifier, Identifier, Colon,
+            Identifier
+        ],
+        kinds
+    );
+}
+
+#[test]
+fn test_peek_token() {
+    let mut scanner = Scanner::new("(k, v)");
+    assert_eq!(Comma, scanner.peek_token(2).kind);
+    assert_eq!(LeftParen, scanner.scan_token().kind);
+    assert_eq!(Identifier, scanner.peek_token(0).kind);
+    assert_eq!(Identifier, scanner.scan_token().kind);
+}
+
 #[test]
 fn test_continue() {
     let mut scanner = Scanner::new("continue");
@@ -484,7 +889,9 @@ fn test_continue() {
         Token {
             kind: Continue,
             lexeme: "continue".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 8)
         },
         token
     );
@@ -498,7 +905,9 @@ fn test_mod() {
         Token {
             kind: Modulo,
             lexeme: "%".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -512,7 +921,9 @@ fn test_power() {
         Token {
             kind: Power,
             lexeme: "^".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -526,7 +937,9 @@ fn test_break() {
         Token {
             kind: Break,
             lexeme: "break".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 5)
         },
         token
     );
@@ -540,7 +953,9 @@ fn test_loop() {
         Token {
             kind: Loop,
             lexeme: "loop".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 4)
         },
         token
     );
@@ -554,7 +969,9 @@ fn test_let() {
         Token {
             kind: Let,
             lexeme: "let".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 3)
         },
         token
     );
@@ -568,7 +985,9 @@ fn test_while() {
         Token {
             kind: While,
             lexeme: "while".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 5)
         },
         token
     );
@@ -582,7 +1001,9 @@ fn test_error_char() {
         Token {
             kind: Error,
             lexeme: "Unexpected character.".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 1)
         },
         token
     );
@@ -596,7 +1017,9 @@ fn test_error_string() {
         Token {
             kind: Error,
             lexeme: "Unterminated string.".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 4)
         },
         token
     );
@@ -610,7 +1033,9 @@ fn test_eof() {
         Token {
             kind: EOF,
             lexeme: "".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 0)
         },
         token
     );
@@ -624,7 +1049,9 @@ fn test_comment() {
         Token {
             kind: EOF,
             lexeme: "".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 7,
+            span: Span::new(6, 6)
         },
         token
     );
@@ -638,7 +1065,9 @@ fn test_whitespace() {
         Token {
             kind: EOF,
             lexeme: "".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 4,
+            span: Span::new(3, 3)
         },
         token
     );
@@ -652,7 +1081,9 @@ fn test_sentence() {
         Token {
             kind: Let,
             lexeme: "let".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 1,
+            span: Span::new(0, 3)
         },
         token
     );
@@ -661,7 +1092,9 @@ fn test_sentence() {
         Token {
             kind: Identifier,
             lexeme: "a".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 5,
+            span: Span::new(4, 5)
         },
         token
     );
@@ -670,7 +1103,9 @@ fn test_sentence() {
         Token {
             kind: Equal,
             lexeme: "=".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 7,
+            span: Span::new(6, 7)
         },
         token
     );
@@ -679,7 +1114,9 @@ fn test_sentence() {
         Token {
             kind: Number,
             lexeme: "2".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 9,
+            span: Span::new(8, 9)
         },
         token
     );
@@ -688,7 +1125,38 @@ fn test_sentence() {
         Token {
             kind: Semicolon,
             lexeme: ";".to_string(),
-            line_number: 1
+            line_number: 1,
+            column: 10,
+            span: Span::new(9, 10)
+        },
+        token
+    );
+}
+
+#[test]
+fn test_is_complete() {
+    assert!(is_complete("let a = 1;"));
+    assert!(is_complete("fn f() { return [1, (2)]; }"));
+    assert!(is_complete("let s = \"a ${ {\"k\": 1}[\"k\"] } b\";"));
+    assert!(!is_complete("fn f() {"));
+    assert!(!is_complete("print (1 +"));
+    assert!(!is_complete("let l = [1,"));
+    assert!(!is_complete("let s = \"open"));
+    assert!(!is_complete("let s = \"a ${b"));
+}
+
+#[test]
+fn test_position_after_multibyte_and_newline() {
+    let mut scanner = Scanner::new("\"é\"\n  ab");
+    scanner.scan_token();
+    let token = scanner.scan_token();
+    assert_eq!(
+        Token {
+            kind: Identifier,
+            lexeme: "ab".to_string(),
+            line_number: 2,
+            column: 3,
+            span: Span::new(7, 9)
         },
         token
     );
@@ -1,45 +1,74 @@
+use crate::diagnostic::Span;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum TokenKind {
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Modulo,
+    ModuloEqual,
     Comma,
+    Pipe,
+    Colon,
+    ColonColon,
     Dot,
+    DotDot,
+    DotDotEqual,
     Power,
+    PowerEqual,
     Minus,
+    MinusEqual,
     Plus,
+    PlusEqual,
     Semicolon,
     Slash,
+    SlashEqual,
     Star,
+    StarEqual,
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
     Identifier,
     String,
+    Interpolation,
     Number,
     And,
+    As,
     Break,
     Struct,
+    Catch,
     Continue,
     Loop,
+    Match,
     Else,
+    Enum,
     False,
+    Finally,
     For,
     Fn,
     If,
+    Import,
+    In,
     None,
     Or,
     Print,
     Return,
     Self_,
+    Static,
+    Super,
+    Throw,
     True,
+    Try,
     Let,
     While,
     Error,
@@ -51,4 +80,6 @@ pub struct Token {
     pub kind: TokenKind,
     pub lexeme: String,
     pub line_number: usize,
+    pub column: usize,
+    pub span: Span,
 }
@@ -0,0 +1,45 @@
+use std::{
+    cell::RefCell,
+    fmt::{Formatter, Result},
+};
+
+// Lists and maps can end up containing themselves, so printing and
+// comparing them keeps track of the ones it's already inside
+thread_local! {
+    static DISPLAYING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    static COMPARING: RefCell<Vec<(usize, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+fn address<T>(object: &T) -> usize {
+    object as *const T as usize
+}
+
+// Writes the object, or the placeholder when reached again from inside
+pub fn display<T>(
+    object: &T,
+    f: &mut Formatter<'_>,
+    placeholder: &str,
+    write: impl FnOnce(&mut Formatter<'_>) -> Result,
+) -> Result {
+    let address = address(object);
+    if DISPLAYING.with_borrow(|displaying| displaying.contains(&address)) {
+        return write!(f, "{}", placeholder);
+    }
+    DISPLAYING.with_borrow_mut(|displaying| displaying.push(address));
+    let result = write(f);
+    DISPLAYING.with_borrow_mut(|displaying| displaying.pop());
+    result
+}
+
+// Compares the objects, taking a pair reached again from inside as equal
+// so far; any difference shows up elsewhere in the comparison
+pub fn eq<T>(a: &T, b: &T, eq: impl FnOnce() -> bool) -> bool {
+    let pair = (address(a), address(b));
+    if COMPARING.with_borrow(|comparing| comparing.contains(&pair)) {
+        return true;
+    }
+    COMPARING.with_borrow_mut(|comparing| comparing.push(pair));
+    let result = eq();
+    COMPARING.with_borrow_mut(|comparing| comparing.pop());
+    result
+}
@@ -0,0 +1,34 @@
+use std::{collections::HashSet, rc::Rc};
+
+// Hands out one shared allocation per distinct string
+#[derive(Default)]
+pub struct Interner {
+    strings: HashSet<Rc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self {
+            strings: HashSet::new(),
+        }
+    }
+
+    pub fn intern(&mut self, string: &str) -> Rc<str> {
+        if let Some(interned) = self.strings.get(string) {
+            return interned.clone();
+        }
+        let interned: Rc<str> = Rc::from(string);
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    // Drop strings nothing but the interner refers to
+    pub fn prune(&mut self) {
+        self.strings.retain(|string| Rc::strong_count(string) > 1);
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+}
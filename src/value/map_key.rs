@@ -0,0 +1,37 @@
+use super::Value;
+use std::{fmt::Display, rc::Rc};
+
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub enum MapKey {
+    Bool(bool),
+    Number(u64),
+    String(Rc<str>),
+}
+
+impl MapKey {
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(MapKey::Bool(*b)),
+            Value::Number(n) if n.is_nan() => None,
+            // -0.0 and 0.0 compare equal, so they must hash to the same key
+            Value::Number(n) if *n == 0.0 => Some(MapKey::Number(0.0f64.to_bits())),
+            Value::Number(n) => Some(MapKey::Number(n.to_bits())),
+            Value::String(s) => Some(MapKey::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            MapKey::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+impl Display for MapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_value())
+    }
+}
@@ -1,26 +1,41 @@
-use crate::object::{
-    bound_method_object::BoundMethodObject, function_object::FunctionObject,
-    instance_object::InstanceObject, native_function_object::NativeFunctionObject,
-    struct_object::StructObject,
+use crate::{
+    object::{
+        bound_method_object::BoundMethodObject, closure_object::ClosureObject,
+        enum_object::EnumObject, function_object::FunctionObject, instance_object::InstanceObject,
+        list_object::ListObject, map_object::MapObject, module_object::ModuleObject,
+        native_function_object::NativeFunctionObject, range_object::RangeObject,
+        struct_object::StructObject, variant_object::VariantObject,
+    },
+    vm::runtime_error::RuntimeError,
 };
 use std::{
-    any::Any,
     cmp::Ordering,
-    fmt::Display,
+    fmt::{Debug, Display},
     ops::{Add, Div, Mul, Neg, Sub},
     rc::Rc,
 };
 
+pub mod cycle;
+pub mod interner;
+pub mod map_key;
+
 pub enum Value {
     None,
     Bool(bool),
     Number(f64),
-    String(String),
+    String(Rc<str>),
     Function(Rc<FunctionObject>),
+    Closure(Rc<ClosureObject>),
     NativeFunction(Rc<dyn NativeFunctionObject>),
     Struct(Rc<StructObject>),
     Instance(Rc<InstanceObject>),
     BoundMethod(Rc<BoundMethodObject>),
+    List(Rc<ListObject>),
+    Map(Rc<MapObject>),
+    Range(Rc<RangeObject>),
+    Enum(Rc<EnumObject>),
+    Variant(Rc<VariantObject>),
+    Module(Rc<ModuleObject>),
 }
 
 impl Display for Value {
@@ -32,14 +47,60 @@ impl Display for Value {
             Number(number) => write!(f, "{}", number),
             String(string) => write!(f, "{}", string),
             Function(function) => write!(f, "{}", function),
-            NativeFunction(_) => write!(f, "<native fn>"),
+            Closure(closure) => write!(f, "{}", closure),
+            NativeFunction(function) => write!(f, "<native fn {}>", function.name()),
             Struct(r#struct) => write!(f, "{}", r#struct),
             Instance(instance) => write!(f, "{}", instance),
             BoundMethod(bound_method) => write!(f, "{}", bound_method),
+            List(list) => write!(f, "{}", list),
+            Map(map) => write!(f, "{}", map),
+            Range(range) => write!(f, "{}", range),
+            Enum(r#enum) => write!(f, "{}", r#enum),
+            Variant(variant) => write!(f, "{}", variant),
+            Module(module) => write!(f, "{}", module),
+        }
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(string) => write!(f, "{:?}", string),
+            value => write!(f, "{}", value),
         }
     }
 }
 
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.into())
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Value::List(Rc::new(ListObject::new(value)))
+    }
+}
+
 impl Clone for Value {
     fn clone(&self) -> Self {
         use Value::*;
@@ -49,10 +110,17 @@ impl Clone for Value {
             Number(n) => Number(*n),
             String(s) => String(s.clone()),
             Function(f) => Function(Rc::clone(f)),
+            Closure(c) => Closure(Rc::clone(c)),
             NativeFunction(n) => NativeFunction(Rc::clone(n)),
             Struct(c) => Struct(Rc::clone(c)),
             Instance(i) => Instance(Rc::clone(i)),
             BoundMethod(b) => BoundMethod(Rc::clone(b)),
+            List(l) => List(Rc::clone(l)),
+            Map(m) => Map(Rc::clone(m)),
+            Range(r) => Range(Rc::clone(r)),
+            Enum(e) => Enum(Rc::clone(e)),
+            Variant(v) => Variant(Rc::clone(v)),
+            Module(m) => Module(Rc::clone(m)),
         }
     }
 }
@@ -64,12 +132,24 @@ impl PartialEq for Value {
             (None, None) => true,
             (Bool(a), Bool(b)) => a == b,
             (Number(a), Number(b)) => a == b,
-            (String(a), String(b)) => a.cmp(b) == Ordering::Equal,
+            (String(a), String(b)) => Rc::ptr_eq(a, b) || a == b,
             (Function(a), Function(b)) => Rc::ptr_eq(a, b),
-            (NativeFunction(a), NativeFunction(b)) => a.type_id() == b.type_id(),
+            (Closure(a), Closure(b)) => Rc::ptr_eq(a, b),
+            (NativeFunction(a), NativeFunction(b)) => Rc::ptr_eq(a, b),
             (Struct(a), Struct(b)) => Rc::ptr_eq(a, b),
             (Instance(a), Instance(b)) => Rc::ptr_eq(a, b),
             (BoundMethod(a), BoundMethod(b)) => Rc::ptr_eq(a, b),
+            (List(a), List(b)) => {
+                Rc::ptr_eq(a, b) || cycle::eq(&**a, &**b, || *a.items.borrow() == *b.items.borrow())
+            }
+            (Map(a), Map(b)) => {
+                Rc::ptr_eq(a, b)
+                    || cycle::eq(&**a, &**b, || *a.entries.borrow() == *b.entries.borrow())
+            }
+            (Range(a), Range(b)) => a == b,
+            (Enum(a), Enum(b)) => Rc::ptr_eq(a, b),
+            (Variant(a), Variant(b)) => a == b,
+            (Module(a), Module(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
@@ -87,95 +167,91 @@ impl PartialOrd for Value {
 }
 
 impl Neg for Value {
-    type Output = Value;
+    type Output = Result<Value, RuntimeError>;
 
     fn neg(self) -> Self::Output {
         use Value::*;
         match self {
-            Number(a) => Number(-a),
-            _ => panic!("Only number can be negated"),
+            Number(a) => Ok(Number(-a)),
+            _ => Err(RuntimeError::new("Operand must be a number.")),
         }
     }
 }
 
 impl Add for Value {
-    type Output = Value;
+    type Output = Result<Value, RuntimeError>;
 
     fn add(self, rhs: Self) -> Self::Output {
         use Value::*;
         match (self, rhs) {
-            (Number(a), Number(b)) => Number(a + b),
-            (Value::String(a), Value::String(b)) => Value::String(a + &b),
-            _ => panic!("Only number can do addition"),
+            (Number(a), Number(b)) => Ok(Number(a + b)),
+            (Value::String(a), Value::String(b)) => Ok(Value::String(format!("{a}{b}").into())),
+            _ => Err(RuntimeError::new(
+                "Operands must be two numbers or two strings.",
+            )),
         }
     }
 }
 
 impl Sub for Value {
-    type Output = Value;
+    type Output = Result<Value, RuntimeError>;
 
     fn sub(self, rhs: Self) -> Self::Output {
         use Value::*;
         match (self, rhs) {
-            (Number(a), Number(b)) => Number(a - b),
-            _ => panic!("Only number can do substration"),
+            (Number(a), Number(b)) => Ok(Number(a - b)),
+            _ => Err(RuntimeError::new("Operands must be two numbers.")),
         }
     }
 }
 
 impl Mul for Value {
-    type Output = Value;
+    type Output = Result<Value, RuntimeError>;
 
     fn mul(self, rhs: Self) -> Self::Output {
         use Value::*;
         match (self, rhs) {
-            (Number(a), Number(b)) => Number(a * b),
-            _ => panic!("Only number can do multiplication"),
+            (Number(a), Number(b)) => Ok(Number(a * b)),
+            _ => Err(RuntimeError::new("Operands must be two numbers.")),
         }
     }
 }
 
 impl Div for Value {
-    type Output = Value;
+    type Output = Result<Value, RuntimeError>;
 
     fn div(self, rhs: Self) -> Self::Output {
         use Value::*;
         match (self, rhs) {
-            (Number(a), Number(b)) => Number(a / b),
-            _ => panic!("Only number can do division"),
+            (Number(a), Number(b)) => Ok(Number(a / b)),
+            _ => Err(RuntimeError::new("Operands must be two numbers.")),
         }
     }
 }
 
 impl Value {
-    pub fn modulo(self, rhs: Self) -> Self {
+    pub fn modulo(self, rhs: Self) -> Result<Self, RuntimeError> {
         use Value::*;
         match (self, rhs) {
-            (Number(a), Number(b)) => Number(a.rem_euclid(b)),
-            _ => panic!("Only number can do modulo"),
+            (Number(a), Number(b)) => Ok(Number(a.rem_euclid(b))),
+            _ => Err(RuntimeError::new("Operands must be two numbers.")),
         }
     }
 
-    pub fn power(self, rhs: Self) -> Self {
+    pub fn power(self, rhs: Self) -> Result<Self, RuntimeError> {
         use Value::*;
         match (self, rhs) {
-            (Number(a), Number(b)) => Number(a.powf(b)),
-            _ => panic!("Only number can do power"),
+            (Number(a), Number(b)) => Ok(Number(a.powf(b))),
+            _ => Err(RuntimeError::new("Operands must be two numbers.")),
         }
     }
 
     pub fn is_number(&self) -> bool {
-        match self {
-            Self::Number(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Number(_))
     }
 
     pub fn is_string(&self) -> bool {
-        match self {
-            Self::String(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::String(_))
     }
 
     pub fn is_falsey(&self) -> bool {
@@ -193,6 +269,12 @@ impl Value {
 
 pub struct ConstantPool(pub Vec<Value>);
 
+impl Default for ConstantPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ConstantPool {
     pub fn new() -> Self {
         ConstantPool(Vec::new())
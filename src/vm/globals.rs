@@ -0,0 +1,78 @@
+use crate::value::Value;
+use std::{collections::HashMap, rc::Rc};
+
+// Global variables live in slots the compiler assigns by name, so the
+// VM reads and writes them by index. An empty slot is an undefined variable.
+#[derive(Default)]
+pub struct Globals {
+    slots: HashMap<Rc<str>, usize>,
+    names: Vec<Rc<str>>,
+    values: Vec<Option<Value>>,
+}
+
+impl Globals {
+    pub fn new() -> Self {
+        Self {
+            slots: HashMap::new(),
+            names: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    // Find the slot for a name, reserving an undefined one if needed
+    pub fn resolve(&mut self, name: Rc<str>) -> usize {
+        if let Some(&slot) = self.slots.get(&name) {
+            return slot;
+        }
+        let slot = self.values.len();
+        self.slots.insert(name.clone(), slot);
+        self.names.push(name);
+        self.values.push(None);
+        slot
+    }
+
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.slots.get(name).copied()
+    }
+
+    pub fn name(&self, slot: usize) -> &Rc<str> {
+        &self.names[slot]
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&Value> {
+        self.values[slot].as_ref()
+    }
+
+    pub fn define(&mut self, slot: usize, value: Value) {
+        self.values[slot] = Some(value);
+    }
+
+    // Assign to an already defined global, returning false if it is not
+    pub fn set(&mut self, slot: usize, value: Value) -> bool {
+        match &mut self.values[slot] {
+            Some(current) => {
+                *current = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<Value> {
+        self.get(self.slot(name)?).cloned()
+    }
+
+    pub fn define_by_name(&mut self, name: Rc<str>, value: Value) {
+        let slot = self.resolve(name);
+        self.define(slot, value);
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.values.iter().flatten()
+    }
+
+    // Undefine every global, keeping the slots the compiler handed out
+    pub fn clear(&mut self) {
+        self.values.iter_mut().for_each(|value| *value = None);
+    }
+}
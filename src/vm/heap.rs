@@ -0,0 +1,346 @@
+use crate::{
+    object::{
+        bound_method_object::BoundMethodObject, closure_object::ClosureObject,
+        instance_object::InstanceObject, list_object::ListObject, map_object::MapObject,
+        module_object::ModuleObject, struct_object::StructObject, upvalue_object::UpvalueObject,
+        variant_object::VariantObject,
+    },
+    value::Value,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::{Rc, Weak},
+};
+
+const DEFAULT_THRESHOLD: usize = 1024;
+const GROWTH_FACTOR: usize = 2;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HeapStats {
+    pub objects: usize,
+    pub threshold: usize,
+    pub collections: usize,
+    pub freed: usize,
+}
+
+enum WeakObject {
+    Closure(Weak<ClosureObject>),
+    Upvalue(Weak<UpvalueObject>),
+    Struct(Weak<StructObject>),
+    Instance(Weak<InstanceObject>),
+    BoundMethod(Weak<BoundMethodObject>),
+    List(Weak<ListObject>),
+    Map(Weak<MapObject>),
+    Variant(Weak<VariantObject>),
+    Module(Weak<ModuleObject>),
+}
+
+impl WeakObject {
+    fn upgrade(&self) -> Option<HeapObject> {
+        Some(match self {
+            WeakObject::Closure(o) => HeapObject::Closure(o.upgrade()?),
+            WeakObject::Upvalue(o) => HeapObject::Upvalue(o.upgrade()?),
+            WeakObject::Struct(o) => HeapObject::Struct(o.upgrade()?),
+            WeakObject::Instance(o) => HeapObject::Instance(o.upgrade()?),
+            WeakObject::BoundMethod(o) => HeapObject::BoundMethod(o.upgrade()?),
+            WeakObject::List(o) => HeapObject::List(o.upgrade()?),
+            WeakObject::Map(o) => HeapObject::Map(o.upgrade()?),
+            WeakObject::Variant(o) => HeapObject::Variant(o.upgrade()?),
+            WeakObject::Module(o) => HeapObject::Module(o.upgrade()?),
+        })
+    }
+
+    fn is_alive(&self) -> bool {
+        match self {
+            WeakObject::Closure(o) => o.strong_count() > 0,
+            WeakObject::Upvalue(o) => o.strong_count() > 0,
+            WeakObject::Struct(o) => o.strong_count() > 0,
+            WeakObject::Instance(o) => o.strong_count() > 0,
+            WeakObject::BoundMethod(o) => o.strong_count() > 0,
+            WeakObject::List(o) => o.strong_count() > 0,
+            WeakObject::Map(o) => o.strong_count() > 0,
+            WeakObject::Variant(o) => o.strong_count() > 0,
+            WeakObject::Module(o) => o.strong_count() > 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum HeapObject {
+    Closure(Rc<ClosureObject>),
+    Upvalue(Rc<UpvalueObject>),
+    Struct(Rc<StructObject>),
+    Instance(Rc<InstanceObject>),
+    BoundMethod(Rc<BoundMethodObject>),
+    List(Rc<ListObject>),
+    Map(Rc<MapObject>),
+    Variant(Rc<VariantObject>),
+    Module(Rc<ModuleObject>),
+}
+
+impl HeapObject {
+    pub fn from_value(value: &Value) -> Option<Self> {
+        Some(match value {
+            Value::Closure(o) => HeapObject::Closure(o.clone()),
+            Value::Struct(o) => HeapObject::Struct(o.clone()),
+            Value::Instance(o) => HeapObject::Instance(o.clone()),
+            Value::BoundMethod(o) => HeapObject::BoundMethod(o.clone()),
+            Value::List(o) => HeapObject::List(o.clone()),
+            Value::Map(o) => HeapObject::Map(o.clone()),
+            Value::Variant(o) => HeapObject::Variant(o.clone()),
+            Value::Module(o) => HeapObject::Module(o.clone()),
+            _ => return None,
+        })
+    }
+
+    fn address(&self) -> usize {
+        match self {
+            HeapObject::Closure(o) => Rc::as_ptr(o) as *const () as usize,
+            HeapObject::Upvalue(o) => Rc::as_ptr(o) as *const () as usize,
+            HeapObject::Struct(o) => Rc::as_ptr(o) as *const () as usize,
+            HeapObject::Instance(o) => Rc::as_ptr(o) as *const () as usize,
+            HeapObject::BoundMethod(o) => Rc::as_ptr(o) as *const () as usize,
+            HeapObject::List(o) => Rc::as_ptr(o) as *const () as usize,
+            HeapObject::Map(o) => Rc::as_ptr(o) as *const () as usize,
+            HeapObject::Variant(o) => Rc::as_ptr(o) as *const () as usize,
+            HeapObject::Module(o) => Rc::as_ptr(o) as *const () as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            HeapObject::Closure(o) => Rc::strong_count(o),
+            HeapObject::Upvalue(o) => Rc::strong_count(o),
+            HeapObject::Struct(o) => Rc::strong_count(o),
+            HeapObject::Instance(o) => Rc::strong_count(o),
+            HeapObject::BoundMethod(o) => Rc::strong_count(o),
+            HeapObject::List(o) => Rc::strong_count(o),
+            HeapObject::Map(o) => Rc::strong_count(o),
+            HeapObject::Variant(o) => Rc::strong_count(o),
+            HeapObject::Module(o) => Rc::strong_count(o),
+        }
+    }
+
+    fn downgrade(&self) -> WeakObject {
+        match self {
+            HeapObject::Closure(o) => WeakObject::Closure(Rc::downgrade(o)),
+            HeapObject::Upvalue(o) => WeakObject::Upvalue(Rc::downgrade(o)),
+            HeapObject::Struct(o) => WeakObject::Struct(Rc::downgrade(o)),
+            HeapObject::Instance(o) => WeakObject::Instance(Rc::downgrade(o)),
+            HeapObject::BoundMethod(o) => WeakObject::BoundMethod(Rc::downgrade(o)),
+            HeapObject::List(o) => WeakObject::List(Rc::downgrade(o)),
+            HeapObject::Map(o) => WeakObject::Map(Rc::downgrade(o)),
+            HeapObject::Variant(o) => WeakObject::Variant(Rc::downgrade(o)),
+            HeapObject::Module(o) => WeakObject::Module(Rc::downgrade(o)),
+        }
+    }
+
+    fn children(&self) -> Vec<HeapObject> {
+        let mut children = Vec::new();
+        match self {
+            HeapObject::Closure(closure) => {
+                children.extend(
+                    closure
+                        .upvalues
+                        .iter()
+                        .map(|upvalue| HeapObject::Upvalue(upvalue.clone())),
+                );
+                children.extend(closure.module.clone().map(HeapObject::Module));
+            }
+            HeapObject::Upvalue(upvalue) => {
+                children.extend(upvalue.closed.borrow().iter().filter_map(Self::from_value))
+            }
+            HeapObject::Struct(r#struct) => {
+                children.extend(
+                    r#struct
+                        .methods
+                        .borrow()
+                        .values()
+                        .map(|method| HeapObject::Closure(method.clone())),
+                );
+                children.extend(
+                    r#struct
+                        .statics
+                        .borrow()
+                        .values()
+                        .map(|function| HeapObject::Closure(function.clone())),
+                );
+                children.extend(
+                    r#struct
+                        .fields
+                        .borrow()
+                        .iter()
+                        .filter_map(|field| field.default.clone().map(HeapObject::Closure)),
+                );
+                if let Some(parent) = r#struct.parent.borrow().as_ref() {
+                    children.push(HeapObject::Struct(parent.clone()));
+                }
+            }
+            HeapObject::Instance(instance) => {
+                children.push(HeapObject::Struct(instance.r#struct.clone()));
+                children.extend(
+                    instance
+                        .fields
+                        .borrow()
+                        .values()
+                        .filter_map(Self::from_value),
+                );
+            }
+            HeapObject::BoundMethod(bound) => {
+                children.extend(Self::from_value(&bound.receiver));
+                children.push(HeapObject::Closure(bound.method.clone()));
+            }
+            HeapObject::List(list) => {
+                children.extend(list.items.borrow().iter().filter_map(Self::from_value))
+            }
+            HeapObject::Map(map) => {
+                children.extend(map.entries.borrow().values().filter_map(Self::from_value))
+            }
+            HeapObject::Variant(variant) => {
+                children.extend(variant.payload.iter().filter_map(Self::from_value))
+            }
+            HeapObject::Module(module) => children.extend(
+                module
+                    .globals
+                    .borrow()
+                    .values()
+                    .filter_map(Self::from_value),
+            ),
+        }
+        children
+    }
+
+    // Drops every reference the object holds, which breaks the cycles
+    // that keep an unreachable object alive.
+    fn clear(&self) {
+        match self {
+            HeapObject::Upvalue(upvalue) => {
+                upvalue.closed.replace(Some(Value::None));
+            }
+            HeapObject::Struct(r#struct) => {
+                r#struct.methods.borrow_mut().clear();
+                r#struct.statics.borrow_mut().clear();
+                r#struct.fields.borrow_mut().clear();
+                r#struct.parent.replace(None);
+            }
+            HeapObject::Instance(instance) => instance.fields.borrow_mut().clear(),
+            HeapObject::List(list) => list.items.borrow_mut().clear(),
+            HeapObject::Map(map) => {
+                map.entries.borrow_mut().clear();
+                map.keys.borrow_mut().clear();
+            }
+            HeapObject::Module(module) => module.globals.borrow_mut().clear(),
+            // A payload is fixed when the variant is built, so any cycle
+            // through one also runs through a list, map or instance
+            HeapObject::Closure(_) | HeapObject::BoundMethod(_) | HeapObject::Variant(_) => {}
+        }
+    }
+}
+
+pub struct Heap {
+    objects: HashMap<usize, WeakObject>,
+    threshold: usize,
+    min_threshold: usize,
+    collections: usize,
+    freed: usize,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: HashMap::new(),
+            threshold: DEFAULT_THRESHOLD,
+            min_threshold: DEFAULT_THRESHOLD,
+            collections: 0,
+            freed: 0,
+        }
+    }
+
+    pub fn track(&mut self, object: HeapObject) {
+        self.objects.insert(object.address(), object.downgrade());
+    }
+
+    pub fn track_value(&mut self, value: &Value) {
+        if let Some(object) = HeapObject::from_value(value) {
+            self.track(object);
+        }
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.objects.len() > self.threshold
+    }
+
+    pub fn set_threshold(&mut self, threshold: usize) {
+        self.threshold = threshold;
+        self.min_threshold = threshold;
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        HeapStats {
+            objects: self.objects.values().filter(|o| o.is_alive()).count(),
+            threshold: self.threshold,
+            collections: self.collections,
+            freed: self.freed,
+        }
+    }
+
+    // Anything referenced from outside the tracked object graph (a Rust
+    // local, a host handle, a native closure) is treated as a root as
+    // well, so only cycles nobody can reach are cleared.
+    pub fn collect(&mut self, roots: Vec<HeapObject>) -> usize {
+        let live = self
+            .objects
+            .values()
+            .filter_map(|object| object.upgrade())
+            .map(|object| (object.address(), object))
+            .collect::<HashMap<usize, HeapObject>>();
+
+        let mut external = live
+            .iter()
+            .map(|(address, object)| (*address, object.strong_count() - 1))
+            .collect::<HashMap<usize, usize>>();
+        for child in live.values().flat_map(|object| object.children()) {
+            if let Some(count) = external.get_mut(&child.address()) {
+                *count = count.saturating_sub(1);
+            }
+        }
+
+        let mut gray = roots;
+        gray.extend(
+            external
+                .iter()
+                .filter(|(_, count)| **count > 0)
+                .map(|(address, _)| live[address].clone()),
+        );
+        let mut marked = HashSet::new();
+        while let Some(object) = gray.pop() {
+            if marked.insert(object.address()) {
+                gray.extend(object.children());
+            }
+        }
+
+        let garbage = live
+            .iter()
+            .filter(|(address, _)| !marked.contains(*address))
+            .map(|(_, object)| object)
+            .collect::<Vec<&HeapObject>>();
+        garbage.iter().for_each(|object| object.clear());
+        drop(garbage);
+        drop(live);
+
+        let before = self.objects.len();
+        self.objects.retain(|_, object| object.is_alive());
+        let freed = before - self.objects.len();
+
+        self.collections += 1;
+        self.freed += freed;
+        self.threshold = (self.objects.len() * GROWTH_FACTOR).max(self.min_threshold);
+        freed
+    }
+}
@@ -3,27 +3,76 @@ use crate::{
     compiler::{Compiler, FunctionKind, InterpretError},
     object::{
         bound_method_object::BoundMethodObject,
-        function_object::FunctionObject,
+        closure_object::ClosureObject,
+        enum_object::EnumObject,
         instance_object::InstanceObject,
+        list_object::ListObject,
+        map_object::MapObject,
+        module_object::ModuleObject,
         native_function_object::{
-            Clock, ConvertToNumber, ConvertToString, NativeFunctionObject, Println,
+            Arity, Clock, ConvertToNumber, ConvertToString, Keys, NativeClosure,
+            NativeFunctionObject, Println,
         },
-        struct_object::StructObject,
+        range_object::RangeObject,
+        struct_object::{StructField, StructObject},
+        upvalue_object::UpvalueObject,
+        variant_object::VariantObject,
     },
-    value::Value,
+    value::{interner::Interner, map_key::MapKey, Value},
 };
-use std::{collections::HashMap, rc::Rc};
+use globals::Globals;
+use heap::{Heap, HeapObject, HeapStats};
+use runtime_error::{error_instance, trace_value, RuntimeError, TraceFrame};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+pub mod globals;
+pub mod heap;
+pub mod runtime_error;
+
+#[cfg(test)]
+mod tests;
 
 struct CallFrame {
-    function: Rc<FunctionObject>,
+    closure: Rc<ClosureObject>,
     ip: usize,
     base_slot: usize,
 }
 
+// A try block that's running: a throw unwinds to its frame and stack
+// height and resumes at its catch code
+struct Handler {
+    frame_depth: usize,
+    stack_len: usize,
+    catch_ip: usize,
+    finally_ip: usize,
+}
+
 pub struct VirtualMachine {
     frames: Vec<CallFrame>,
     stack: Vec<Value>,
-    globals: HashMap<String, Value>,
+    globals: Rc<RefCell<Globals>>,
+    // Native functions, the only globals modules see besides their own
+    builtins: Globals,
+    interner: Rc<RefCell<Interner>>,
+    open_upvalues: Vec<Rc<UpvalueObject>>,
+    handlers: Vec<Handler>,
+    error_struct: Rc<StructObject>,
+    heap: Heap,
+    script_path: Option<PathBuf>,
+    modules: HashMap<PathBuf, Rc<ModuleObject>>,
+    importing: Vec<PathBuf>,
+}
+
+impl Default for VirtualMachine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VirtualMachine {
@@ -31,16 +80,31 @@ impl VirtualMachine {
         let mut result = Self {
             frames: Vec::new(),
             stack: Vec::new(),
-            globals: HashMap::new(),
+            globals: Rc::new(RefCell::new(Globals::new())),
+            builtins: Globals::new(),
+            interner: Rc::new(RefCell::new(Interner::new())),
+            open_upvalues: Vec::new(),
+            handlers: Vec::new(),
+            error_struct: Rc::new(StructObject::new("Error".to_string())),
+            heap: Heap::new(),
+            script_path: Option::None,
+            modules: HashMap::new(),
+            importing: Vec::new(),
         };
         let clock = Rc::new(Clock {});
         let println = Rc::new(Println {});
         let convert_to_string = Rc::new(ConvertToString {});
         let convert_to_number = Rc::new(ConvertToNumber {});
+        let keys = Rc::new(Keys {});
         result.define_native("clock", clock.clone());
         result.define_native("println", println.clone());
         result.define_native("String", convert_to_string.clone());
         result.define_native("Number", convert_to_number.clone());
+        result.define_native("keys", keys.clone());
+        let error_struct = result.error_struct.clone();
+        result.register_fn("Error", Arity::Exact(1), move |args| {
+            Ok(error_instance(&error_struct, &args[0].to_string(), &[]))
+        });
         result
     }
 
@@ -49,15 +113,212 @@ impl VirtualMachine {
     }
 
     pub fn interpret(&mut self, source: &str) -> Result<(), InterpretError> {
-        let compiler = Compiler::new(FunctionKind::Script);
+        self.run_source(source, false)?;
+        Ok(())
+    }
+
+    // Like interpret, but yields the value of a trailing expression statement
+    pub fn eval(&mut self, source: &str) -> Result<Value, InterpretError> {
+        self.run_source(source, true)
+    }
+
+    fn run_source(
+        &mut self,
+        source: &str,
+        return_last_value: bool,
+    ) -> Result<Value, InterpretError> {
+        let mut compiler = Compiler::new(
+            FunctionKind::Script,
+            self.interner.clone(),
+            self.globals.clone(),
+        );
+        if return_last_value {
+            compiler.return_last_value();
+        }
+
+        let function = compiler
+            .compile(source)
+            .map_err(InterpretError::CompileError)?;
+        let function = Rc::new(function);
+        let closure = Rc::new(ClosureObject::new(function));
+        self.heap.track(HeapObject::Closure(closure.clone()));
+        self.stack.push(Value::Closure(closure.clone()));
+        self.call(closure, 0)?;
+        if let Err(error) = self.run(0) {
+            self.reset();
+            return Err(error);
+        }
+        Ok(self.stack.pop().unwrap_or(Value::None))
+    }
+
+    pub fn interpret_with_path(
+        &mut self,
+        source: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<(), InterpretError> {
+        let path = path.as_ref();
+        self.script_path = Some(path.to_path_buf());
+        let canonical = path.canonicalize().ok();
+        self.importing.extend(canonical.clone());
+        let result = self.interpret(source);
+        if canonical.is_some() {
+            self.importing.pop();
+        }
+        result
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().get_by_name(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.heap.track_value(&value);
+        let name = self.interner.borrow_mut().intern(name);
+        self.globals.borrow_mut().define_by_name(name, value);
+    }
+
+    pub fn collect_garbage(&mut self) -> usize {
+        let mut roots = Vec::new();
+        roots.extend(self.stack.iter().filter_map(HeapObject::from_value));
+        roots.extend(
+            self.globals
+                .borrow()
+                .values()
+                .filter_map(HeapObject::from_value),
+        );
+        roots.extend(
+            self.modules
+                .values()
+                .map(|module| HeapObject::Module(module.clone())),
+        );
+        roots.extend(
+            self.frames
+                .iter()
+                .map(|frame| HeapObject::Closure(frame.closure.clone())),
+        );
+        roots.extend(
+            self.open_upvalues
+                .iter()
+                .map(|upvalue| HeapObject::Upvalue(upvalue.clone())),
+        );
+        let freed = self.heap.collect(roots);
+        self.interner.borrow_mut().prune();
+        freed
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.heap.stats()
+    }
+
+    pub fn set_gc_threshold(&mut self, threshold: usize) {
+        self.heap.set_threshold(threshold);
+    }
+
+    pub fn register_fn<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, RuntimeError> + 'static,
+    {
+        let native = NativeClosure::new(name, arity, function);
+        self.define_native(name, Rc::new(native));
+    }
+
+    pub fn call_global(&mut self, name: &str, args: &[Value]) -> Result<Value, InterpretError> {
+        match self.get_global(name) {
+            Some(function) => self.call_function(&function, args),
+            Option::None => self.runtime_error(&format!("Undefined variable '{}'", name)),
+        }
+    }
+
+    pub fn call_function(
+        &mut self,
+        function: &Value,
+        args: &[Value],
+    ) -> Result<Value, InterpretError> {
+        if args.len() > u8::MAX.into() {
+            return self.runtime_error("Can't have more than 255 arguments.");
+        }
+
+        let exit_depth = self.frames.len();
+        self.heap.track_value(function);
+        args.iter().for_each(|arg| self.heap.track_value(arg));
+        self.stack.push(function.clone());
+        self.stack.extend(args.iter().cloned());
+        let result = self
+            .call_value(function.clone(), args.len() as u8)
+            .and_then(|()| {
+                if self.frames.len() > exit_depth {
+                    self.run(exit_depth)
+                } else {
+                    Ok(())
+                }
+            });
+        if let Err(error) = result {
+            self.reset();
+            return Err(error);
+        }
+        Ok(self.stack.pop().unwrap())
+    }
+
+    // Errors unwind to the innermost try block started by this run, or
+    // escape to the caller if there is none
+    fn run(&mut self, exit_depth: usize) -> Result<(), InterpretError> {
+        loop {
+            match self.execute(exit_depth) {
+                Err(InterpretError::RuntimeError(error))
+                    if self
+                        .handlers
+                        .last()
+                        .is_some_and(|handler| handler.frame_depth > exit_depth) =>
+                {
+                    self.catch(error)
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Returns whether the run has finished. A return inside a try block
+    // runs the finally block first, which returns again when it ends
+    fn return_value(&mut self, result: Value, exit_depth: usize) -> bool {
+        let frame_depth = self.frames.len();
+        if self
+            .handlers
+            .last()
+            .is_some_and(|handler| handler.frame_depth == frame_depth)
+        {
+            let handler = self.handlers.pop().unwrap();
+            self.close_upvalues(handler.stack_len);
+            self.stack.truncate(handler.stack_len);
+            self.stack.push(result);
+            self.stack.push(Value::None);
+            self.current_frame().ip = handler.finally_ip;
+            return false;
+        }
 
-        let function = Rc::new(compiler.compile(source)?);
-        self.stack.push(Value::Function(function.clone()));
-        self.call(function.clone(), 0);
-        self.run()
+        let frame = self.frames.pop().unwrap();
+        self.close_upvalues(frame.base_slot);
+        self.stack.truncate(frame.base_slot);
+        self.stack.push(result);
+        self.frames.len() == exit_depth
     }
 
-    pub fn run(&mut self) -> Result<(), InterpretError> {
+    fn catch(&mut self, error: RuntimeError) {
+        let handler = self.handlers.pop().unwrap();
+        let value = match error.value {
+            Some(value) => value,
+            Option::None => {
+                let value = error_instance(&self.error_struct, &error.message, &error.trace);
+                self.track(value)
+            }
+        };
+        self.frames.truncate(handler.frame_depth);
+        self.close_upvalues(handler.stack_len);
+        self.stack.truncate(handler.stack_len);
+        self.stack.push(value);
+        self.current_frame().ip = handler.catch_ip;
+    }
+
+    fn execute(&mut self, exit_depth: usize) -> Result<(), InterpretError> {
         use OpCode::*;
         loop {
             #[cfg(feature = "debug_mode")]
@@ -73,6 +334,10 @@ impl VirtualMachine {
                 self.current_chunk().disassemble_instruction(ip);
             }
 
+            if self.heap.should_collect() {
+                self.collect_garbage();
+            }
+
             let instruction: OpCode = self.read_one_bytecode().into();
             match instruction {
                 Print => {
@@ -94,22 +359,58 @@ impl VirtualMachine {
                 }
                 Call => {
                     let arg_count = self.read_one_bytecode();
-                    if !self.call_value(self.peek(arg_count as usize), arg_count) {
-                        return Err(InterpretError::RuntimeError);
-                    }
+                    self.call_value(self.peek(arg_count as usize), arg_count)?;
                 }
                 Return => {
                     let result = self.stack.pop().unwrap();
-                    let frame = self.frames.pop().unwrap();
-                    if self.frames.len() == 0 {
-                        self.stack.pop();
+                    if self.return_value(result, exit_depth) {
                         return Ok(());
                     }
-                    self.stack.truncate(frame.base_slot);
-                    self.stack.push(result);
                 }
-                Constant => {
-                    let constant = self.read_one_constant();
+                Closure | ClosureLong => {
+                    if let Value::Function(function) = self.read_constant(instruction) {
+                        let mut closure = ClosureObject::new(function);
+                        closure.module = self.current_frame().closure.module.clone();
+                        for _ in 0..closure.function.upvalue_count {
+                            let is_local = self.read_one_bytecode() == 1;
+                            let index = self.read_one_bytecode() as usize;
+                            let upvalue = if is_local {
+                                let location = self.current_frame().base_slot + index;
+                                self.capture_upvalue(location)
+                            } else {
+                                self.current_frame().closure.upvalues[index].clone()
+                            };
+                            closure.upvalues.push(upvalue);
+                        }
+                        let closure = self.track(Value::Closure(Rc::new(closure)));
+                        self.stack.push(closure);
+                    }
+                }
+                GetUpvalue => {
+                    let slot = self.read_one_bytecode() as usize;
+                    let upvalue = self.current_frame().closure.upvalues[slot].clone();
+                    let value = match upvalue.closed.borrow().as_ref() {
+                        Some(value) => value.clone(),
+                        Option::None => self.stack[upvalue.location].clone(),
+                    };
+                    self.stack.push(value);
+                }
+                SetUpvalue => {
+                    let slot = self.read_one_bytecode() as usize;
+                    let upvalue = self.current_frame().closure.upvalues[slot].clone();
+                    let value = self.peek(0);
+                    if upvalue.is_open() {
+                        self.stack[upvalue.location] = value;
+                    } else {
+                        upvalue.closed.replace(Some(value));
+                    }
+                }
+                CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.stack.pop();
+                }
+                Constant | ConstantLong => {
+                    let constant = self.read_constant(instruction);
                     self.stack.push(constant);
                 }
                 None => self.stack.push(Value::None),
@@ -118,10 +419,16 @@ impl VirtualMachine {
                 Pop => {
                     self.stack.pop();
                 }
-                Struct => {
-                    if let Value::String(s) = self.read_one_constant() {
-                        let new_struct = StructObject::new(s);
-                        self.stack.push(Value::Struct(Rc::new(new_struct)));
+                Dup => {
+                    let count = self.read_one_bytecode() as usize;
+                    let top = self.stack[self.stack.len() - count..].to_vec();
+                    self.stack.extend(top);
+                }
+                Struct | StructLong => {
+                    if let Value::String(s) = self.read_constant(instruction) {
+                        let new_struct = StructObject::new(s.to_string());
+                        let new_struct = self.track(Value::Struct(Rc::new(new_struct)));
+                        self.stack.push(new_struct);
                     }
                 }
                 GetLocal => {
@@ -135,54 +442,86 @@ impl VirtualMachine {
                     let index = self.current_frame().base_slot + slot as usize;
                     self.stack[index] = self.peek(0);
                 }
-                GetGlobal => {
-                    if let Value::String(name) = self.read_one_constant() {
-                        if let Some(value) = self.globals.get(&name) {
-                            self.stack.push(value.clone());
-                        } else {
+                GetGlobal | GetGlobalLong => {
+                    let slot = self.read_operand(instruction);
+                    match self.global(slot) {
+                        Some(value) => self.stack.push(value),
+                        Option::None => {
+                            let name = self.global_name(slot);
                             return self.runtime_error(&format!("Undefined variable '{}'", name));
                         }
-                    } else {
-                        return self.runtime_error("No identifier name");
                     }
                 }
-                DefineGlobal => {
-                    if let Value::String(name) = self.read_one_constant() {
-                        self.globals.insert(name, self.peek(0));
-                        self.stack.pop();
-                    } else {
-                        return self.runtime_error("No identifier name");
+                DefineGlobal | DefineGlobalLong => {
+                    let slot = self.read_operand(instruction);
+                    let value = self.stack.pop().unwrap();
+                    match self.current_module() {
+                        Some(module) => module.globals.borrow_mut().define(slot, value),
+                        Option::None => self.globals.borrow_mut().define(slot, value),
                     }
                 }
-                SetGlobal => {
-                    if let Value::String(name) = self.read_one_constant() {
-                        if let Option::None = self.globals.insert(name.clone(), self.peek(0)) {
-                            self.globals.remove(&name);
-                            return self.runtime_error(&format!("Undefined variable '{}'", name));
-                        }
-                    } else {
-                        return self.runtime_error("No identifier name");
+                SetGlobal | SetGlobalLong => {
+                    let slot = self.read_operand(instruction);
+                    if !self.set_defined_global(slot, self.peek(0)) {
+                        let name = self.global_name(slot);
+                        return self.runtime_error(&format!("Undefined variable '{}'", name));
                     }
                 }
-                GetProperty => {
-                    if let Value::Instance(instance) = self.peek(0) {
-                        if let Value::String(s) = self.read_one_constant() {
-                            if let Some(value) = instance.fields.borrow().get(&s) {
+                GetProperty | GetPropertyLong => {
+                    if let Value::Module(module) = self.peek(0) {
+                        if let Value::String(name) = self.read_constant(instruction) {
+                            let value = self.module_export(&module, &name)?;
+                            self.stack.pop();
+                            self.stack.push(value);
+                        }
+                    } else if let Value::Instance(instance) = self.peek(0) {
+                        if let Value::String(s) = self.read_constant(instruction) {
+                            let field = instance.fields.borrow().get(&s).cloned();
+                            if let Some(value) = field {
                                 self.stack.pop();
-                                self.stack.push(value.clone());
+                                self.stack.push(value);
                             } else {
-                                if !self.bind_method(instance.r#struct.clone(), &s) {
-                                    return Err(InterpretError::RuntimeError);
-                                }
+                                self.bind_method(instance.r#struct.clone(), &s)?;
                             }
                         }
+                    } else if let Value::Struct(r#struct) = self.peek(0) {
+                        if let Value::String(name) = self.read_constant(instruction) {
+                            let function = self.static_function(&r#struct, &name)?;
+                            self.stack.pop();
+                            self.stack.push(Value::Closure(function));
+                        }
+                    } else if let Value::Enum(r#enum) = self.peek(0) {
+                        if let Value::String(name) = self.read_constant(instruction) {
+                            let value = self.enum_variant(&r#enum, &name)?;
+                            self.stack.pop();
+                            self.stack.push(value);
+                        }
+                    } else if let Value::Variant(variant) = self.peek(0) {
+                        if let Value::String(name) = self.read_constant(instruction) {
+                            let Some(value) = variant.field(&name) else {
+                                return self.runtime_error(&format!(
+                                    "{}.{} has no field '{}'.",
+                                    variant.r#enum,
+                                    variant.variant().name,
+                                    name
+                                ));
+                            };
+                            self.stack.pop();
+                            self.stack.push(value);
+                        }
                     } else {
                         return self.runtime_error("Only instances have properties.");
                     }
                 }
-                SetProperty => {
+                SetProperty | SetPropertyLong => {
                     if let Value::Instance(instance) = self.peek(1) {
-                        if let Value::String(name) = self.read_one_constant() {
+                        if let Value::String(name) = self.read_constant(instruction) {
+                            if !instance.r#struct.accepts_field(&name) {
+                                return self.runtime_error(&format!(
+                                    "{} has no field '{}'.",
+                                    instance.r#struct, name
+                                ));
+                            }
                             instance.fields.borrow_mut().insert(name, self.peek(0));
                             let value = self.stack.pop().unwrap();
                             self.stack.pop();
@@ -192,19 +531,144 @@ impl VirtualMachine {
                         return self.runtime_error("Only instances have fields.");
                     }
                 }
-                Invoke => {
-                    if let Value::String(method) = self.read_one_constant() {
+                Invoke | InvokeLong => {
+                    if let Value::String(method) = self.read_constant(instruction) {
                         let arg_count = self.read_one_bytecode();
-                        if !self.invoke(&method, arg_count as usize) {
-                            return Err(InterpretError::RuntimeError);
-                        }
+                        self.invoke(&method, arg_count as usize)?;
                     }
                 }
-                Method => {
-                    if let Value::String(name) = self.read_one_constant() {
+                Method | MethodLong => {
+                    if let Value::String(name) = self.read_constant(instruction) {
                         self.define_method(name);
                     }
                 }
+                StaticMethod | StaticMethodLong => {
+                    if let Value::String(name) = self.read_constant(instruction) {
+                        if let Value::Closure(function) = self.stack.pop().unwrap() {
+                            if let Value::Struct(r#struct) = self.peek(0) {
+                                r#struct.statics.borrow_mut().insert(name, function);
+                            }
+                        }
+                    }
+                }
+                Field | FieldLong => {
+                    if let Value::String(name) = self.read_constant(instruction) {
+                        let has_default = self.read_one_bytecode() == 1;
+                        let default = match has_default.then(|| self.stack.pop().unwrap()) {
+                            Some(Value::Closure(default)) => Some(default),
+                            _ => Option::None,
+                        };
+                        if let Value::Struct(r#struct) = self.peek(0) {
+                            let field = StructField { name, default };
+                            r#struct.fields.borrow_mut().push(field);
+                        }
+                    }
+                }
+                BuildInstance => {
+                    let field_count = self.read_one_bytecode() as usize;
+                    let entries = self.stack.split_off(self.stack.len() - field_count * 2);
+                    let Value::Struct(r#struct) = self.stack.pop().unwrap() else {
+                        return self.runtime_error("Only structs can be built with a literal.");
+                    };
+                    let instance = self.build_instance(r#struct, entries)?;
+                    self.stack.push(instance);
+                }
+                Inherit => {
+                    if let Value::Struct(parent) = self.peek(1) {
+                        if let Value::Struct(child) = self.peek(0) {
+                            child.inherit(parent);
+                            self.stack.pop();
+                        }
+                    } else {
+                        return self.runtime_error("Parent must be a struct.");
+                    }
+                }
+                GetSuper | GetSuperLong => {
+                    if let Value::String(name) = self.read_constant(instruction) {
+                        if let Some(Value::Struct(parent)) = self.stack.pop() {
+                            self.bind_method(parent, &name)?;
+                        }
+                    }
+                }
+                SuperInvoke | SuperInvokeLong => {
+                    if let Value::String(method) = self.read_constant(instruction) {
+                        let arg_count = self.read_one_bytecode();
+                        if let Some(Value::Struct(parent)) = self.stack.pop() {
+                            self.invoke_from_struct(parent, &method, arg_count as usize)?;
+                        }
+                    }
+                }
+                BuildList => {
+                    let item_count = self.read_one_bytecode() as usize;
+                    let items = self.stack.split_off(self.stack.len() - item_count);
+                    let list = self.track(Value::List(Rc::new(ListObject::new(items))));
+                    self.stack.push(list);
+                }
+                Import | ImportLong => {
+                    if let Value::String(path) = self.read_constant(instruction) {
+                        let module = self.import(&path)?;
+                        self.stack.push(Value::Module(module));
+                    }
+                }
+                Stringify => {
+                    let value = self.stack.pop().unwrap();
+                    let string = match value {
+                        Value::String(_) => value,
+                        value => Value::String(value.to_string().into()),
+                    };
+                    self.stack.push(string);
+                }
+                BuildMap => {
+                    let entry_count = self.read_one_bytecode() as usize;
+                    let entries = self.stack.split_off(self.stack.len() - entry_count * 2);
+                    let map = MapObject::new();
+                    for entry in entries.chunks(2) {
+                        let key = self.map_key(&entry[0])?;
+                        map.insert(key, entry[1].clone());
+                    }
+                    let map = self.track(Value::Map(Rc::new(map)));
+                    self.stack.push(map);
+                }
+                GetIndex => {
+                    let index = self.stack.pop().unwrap();
+                    let target = self.stack.pop().unwrap();
+                    let value = match target {
+                        Value::List(list) => {
+                            let slot = self.list_index(&list, &index)?;
+                            let value = list.items.borrow()[slot].clone();
+                            value
+                        }
+                        Value::Map(map) => {
+                            let key = self.map_key(&index)?;
+                            match map.get(&key) {
+                                Some(value) => value,
+                                Option::None => {
+                                    return self
+                                        .runtime_error(&format!("Undefined key '{}'.", key));
+                                }
+                            }
+                        }
+                        _ => return self.runtime_error("Only lists and maps can be indexed."),
+                    };
+                    self.stack.push(value);
+                }
+                SetIndex => {
+                    let value = self.stack.pop().unwrap();
+                    let index = self.stack.pop().unwrap();
+                    let target = self.stack.pop().unwrap();
+                    match target {
+                        Value::List(list) => {
+                            let slot = self.list_index(&list, &index)?;
+                            list.items.borrow_mut()[slot] = value.clone();
+                        }
+                        Value::Map(map) => {
+                            let key = self.map_key(&index)?;
+                            map.insert(key, value.clone());
+                        }
+                        _ => return self.runtime_error("Only lists and maps can be indexed."),
+                    }
+                    self.stack.push(value);
+                }
                 Equal => {
                     let b = self.stack.pop().unwrap();
                     let a = self.stack.pop().unwrap();
@@ -223,46 +687,379 @@ impl VirtualMachine {
                     self.stack.push(Value::Bool(value));
                 }
                 Negate => {
-                    if !self.peek(0).is_number() {
-                        return self.runtime_error("Operand must be a number.");
+                    let value = self.stack.pop().unwrap();
+                    match -value {
+                        Ok(value) => self.stack.push(value),
+                        Err(error) => return self.runtime_error(&error.message),
                     }
-
+                }
+                PushHandler => {
+                    let offset = self.read_two_bytecodes() as usize;
+                    let catch_ip = self.current_frame().ip + offset;
+                    let offset = self.read_two_bytecodes() as usize;
+                    let finally_ip = self.current_frame().ip + offset;
+                    let slots = self.read_one_bytecode() as usize;
+                    let handler = Handler {
+                        frame_depth: self.frames.len(),
+                        stack_len: self.current_frame().base_slot + slots,
+                        catch_ip,
+                        finally_ip,
+                    };
+                    self.handlers.push(handler);
+                }
+                PopHandler => {
+                    self.handlers.pop();
+                }
+                Throw => {
                     let value = self.stack.pop().unwrap();
-                    self.stack.push(-value);
+                    return self.throw(value);
+                }
+                Range | RangeInclusive => {
+                    let end = self.stack.pop().unwrap();
+                    let start = self.stack.pop().unwrap();
+                    let (Value::Number(start), Value::Number(end)) = (start, end) else {
+                        return self.runtime_error("Range bounds must be numbers.");
+                    };
+                    let range = RangeObject::new(start, end, instruction == RangeInclusive);
+                    self.stack.push(Value::Range(Rc::new(range)));
+                }
+                Iter => {
+                    let iterable = self.stack.pop().unwrap();
+                    let iterator = self.iterator(iterable)?;
+                    self.stack.push(iterator);
+                }
+                ForIter => {
+                    let slot = self.read_one_bytecode() as usize;
+                    let unpack = self.read_one_bytecode() == 2;
+                    let offset = self.read_two_bytecodes() as usize;
+                    let slot = self.current_frame().base_slot + slot;
+                    let iterator = self.stack[slot].clone();
+                    let Value::Number(cursor) = self.stack[slot + 1] else {
+                        unreachable!("for loop cursor is always a number");
+                    };
+                    if self.push_next(&iterator, cursor as usize, unpack)? {
+                        self.stack[slot + 1] = Value::Number(cursor + 1.0);
+                    } else {
+                        self.current_frame().ip += offset;
+                    }
+                }
+                IsInstance => {
+                    let Value::Struct(r#struct) = self.stack.pop().unwrap() else {
+                        return self.runtime_error("Only structs can be used in struct patterns.");
+                    };
+                    let value = self.stack.pop().unwrap();
+                    let is_instance = match value {
+                        Value::Instance(instance) => instance.r#struct.is_a(&r#struct),
+                        _ => false,
+                    };
+                    self.stack.push(Value::Bool(is_instance));
+                }
+                MatchList => {
+                    let len = self.read_one_bytecode() as usize;
+                    let has_rest = self.read_one_bytecode() == 1;
+                    let matches = match self.stack.pop().unwrap() {
+                        Value::List(list) => {
+                            let actual = list.items.borrow().len();
+                            actual == len || has_rest && actual > len
+                        }
+                        _ => false,
+                    };
+                    self.stack.push(Value::Bool(matches));
                 }
-                _ => return Err(InterpretError::RuntimeError),
+                IsVariant => {
+                    let field_count = self.read_one_bytecode() as usize;
+                    let has_fields = self.read_one_bytecode() == 1;
+                    let Value::String(name) = self.stack.pop().unwrap() else {
+                        unreachable!("variant patterns always name a variant");
+                    };
+                    let Value::Enum(r#enum) = self.stack.pop().unwrap() else {
+                        return self.runtime_error("Only enums can be used in variant patterns.");
+                    };
+                    let Some(index) = r#enum.find_variant(&name) else {
+                        return self
+                            .runtime_error(&format!("{} has no variant '{}'.", r#enum, name));
+                    };
+                    // A pattern without parentheses matches any payload
+                    let fields = r#enum.variants[index].fields.len();
+                    if has_fields && field_count != fields {
+                        return self.runtime_error(&format!(
+                            "{}.{} has {} fields but the pattern has {}.",
+                            r#enum, name, fields, field_count
+                        ));
+                    }
+                    let is_variant = match self.stack.pop().unwrap() {
+                        Value::Variant(variant) => {
+                            Rc::ptr_eq(&variant.r#enum, &r#enum) && variant.index == index
+                        }
+                        _ => false,
+                    };
+                    self.stack.push(Value::Bool(is_variant));
+                }
+                GetPayload => {
+                    let index = self.read_one_bytecode() as usize;
+                    let Value::Variant(variant) = self.stack.pop().unwrap() else {
+                        unreachable!("payloads are only read after a variant test");
+                    };
+                    self.stack.push(variant.payload[index].clone());
+                }
+                MatchError => {
+                    let value = self.peek(0);
+                    return self.runtime_error(&format!("No match arm matches {}.", value));
+                }
+                EndFinally => {
+                    let break_offset = self.read_two_bytecodes() as usize;
+                    let continue_offset = self.read_two_bytecodes() as usize;
+                    let next = self.stack.pop().unwrap();
+                    let value = self.stack.pop().unwrap();
+                    match next {
+                        Value::Bool(true) => return self.throw(value),
+                        Value::None if self.return_value(value, exit_depth) => return Ok(()),
+                        Value::Number(0.0) => self.current_frame().ip += break_offset,
+                        Value::Number(_) => self.current_frame().ip += continue_offset,
+                        _ => {}
+                    }
+                }
+                _ => return self.runtime_error(&format!("Unknown opcode '{}'.", instruction)),
             }
         }
     }
 
     fn current_chunk(&mut self) -> &Chunk {
-        &self.current_frame().function.chunk
+        &self.current_frame().closure.function.chunk
+    }
+
+    fn current_module(&mut self) -> Option<Rc<ModuleObject>> {
+        self.current_frame().closure.module.clone()
+    }
+
+    // Module code falls back to the builtins by name, but never sees the
+    // globals of the script importing it
+    fn global(&mut self, slot: usize) -> Option<Value> {
+        let Some(module) = self.current_module() else {
+            return self.globals.borrow().get(slot).cloned();
+        };
+        let globals = module.globals.borrow();
+        globals
+            .get(slot)
+            .cloned()
+            .or_else(|| self.builtins.get_by_name(globals.name(slot)))
+    }
+
+    // Builtins can't be assigned to from module code
+    fn set_defined_global(&mut self, slot: usize, value: Value) -> bool {
+        match self.current_module() {
+            Some(module) => module.globals.borrow_mut().set(slot, value),
+            Option::None => self.globals.borrow_mut().set(slot, value),
+        }
+    }
+
+    fn global_name(&mut self, slot: usize) -> Rc<str> {
+        match self.current_module() {
+            Some(module) => module.globals.borrow().name(slot).clone(),
+            Option::None => self.globals.borrow().name(slot).clone(),
+        }
+    }
+
+    fn import(&mut self, path: &str) -> Result<Rc<ModuleObject>, InterpretError> {
+        let base = match self.current_module() {
+            Some(module) => module.path.parent().map(Path::to_path_buf),
+            Option::None => self
+                .script_path
+                .as_ref()
+                .and_then(|path| path.parent().map(Path::to_path_buf)),
+        };
+        let resolved = match base.unwrap_or_default().join(path).canonicalize() {
+            Ok(resolved) => resolved,
+            Err(error) => {
+                return self.runtime_error(&format!("Could not import '{}': {}.", path, error));
+            }
+        };
+
+        if let Some(start) = self.importing.iter().position(|p| *p == resolved) {
+            let cycle: Vec<String> = self.importing[start..]
+                .iter()
+                .chain(std::iter::once(&resolved))
+                .map(|p| {
+                    p.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            return self.runtime_error(&format!("Cyclic import: {}.", cycle.join(" -> ")));
+        }
+        if let Some(module) = self.modules.get(&resolved) {
+            return Ok(module.clone());
+        }
+
+        let source = match fs::read_to_string(&resolved) {
+            Ok(source) => source,
+            Err(error) => {
+                return self.runtime_error(&format!("Could not import '{}': {}.", path, error));
+            }
+        };
+        let name = resolved
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let module = Rc::new(ModuleObject::new(name, resolved.clone()));
+        self.heap.track(HeapObject::Module(module.clone()));
+        let compiler = Compiler::new(
+            FunctionKind::Script,
+            self.interner.clone(),
+            module.globals.clone(),
+        );
+        let function = match compiler.compile(&source) {
+            Ok(function) => function,
+            Err(diagnostics) => {
+                let errors: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+                return self.runtime_error(&format!(
+                    "Could not compile module '{}':\n{}",
+                    path,
+                    errors.join("\n")
+                ));
+            }
+        };
+        let mut closure = ClosureObject::new(Rc::new(function));
+        closure.module = Some(module.clone());
+        let closure = Rc::new(closure);
+        self.heap.track(HeapObject::Closure(closure.clone()));
+        self.modules.insert(resolved.clone(), module.clone());
+        self.importing.push(resolved.clone());
+
+        let exit_depth = self.frames.len();
+        self.stack.push(Value::Closure(closure.clone()));
+        let result = self.call(closure, 0).and_then(|()| self.run(exit_depth));
+        self.importing.pop();
+        if let Err(error) = result {
+            self.modules.remove(&resolved);
+            return Err(error);
+        }
+        self.stack.pop();
+        Ok(module)
+    }
+
+    fn module_export(
+        &mut self,
+        module: &ModuleObject,
+        name: &str,
+    ) -> Result<Value, InterpretError> {
+        match module.get(name) {
+            Some(value) => Ok(value),
+            Option::None => self.runtime_error(&format!(
+                "Undefined name '{}' in module '{}'.",
+                name, module.name
+            )),
+        }
+    }
+
+    fn static_function(
+        &mut self,
+        r#struct: &StructObject,
+        name: &str,
+    ) -> Result<Rc<ClosureObject>, InterpretError> {
+        match r#struct.find_static(name) {
+            Some(function) => Ok(function),
+            Option::None => {
+                self.runtime_error(&format!("{} has no static function '{}'.", r#struct, name))
+            }
+        }
     }
 
-    fn bind_method(&mut self, structt: Rc<StructObject>, name: &str) -> bool {
-        if let Some(method) = structt.methods.borrow().get(name) {
+    // A variant without fields is a value already, one with fields is a
+    // function building it from its payload
+    fn enum_variant(
+        &mut self,
+        r#enum: &Rc<EnumObject>,
+        name: &str,
+    ) -> Result<Value, InterpretError> {
+        let Some(index) = r#enum.find_variant(name) else {
+            return self.runtime_error(&format!("{} has no variant '{}'.", r#enum, name));
+        };
+        let fields = r#enum.variants[index].fields.len();
+        if fields == 0 {
+            let variant = VariantObject::new(r#enum.clone(), index, Vec::new());
+            return Ok(Value::Variant(Rc::new(variant)));
+        }
+        let constructor = {
+            let r#enum = r#enum.clone();
+            move |args: &[Value]| {
+                let variant = VariantObject::new(r#enum.clone(), index, args.to_vec());
+                Ok(Value::Variant(Rc::new(variant)))
+            }
+        };
+        let name = format!("{}.{}", r#enum, name);
+        let native = NativeClosure::new(&name, Arity::Exact(fields), constructor);
+        Ok(Value::NativeFunction(Rc::new(native)))
+    }
+
+    fn capture_upvalue(&mut self, location: usize) -> Rc<UpvalueObject> {
+        if let Some(upvalue) = self
+            .open_upvalues
+            .iter()
+            .find(|upvalue| upvalue.location == location)
+        {
+            return upvalue.clone();
+        }
+        let upvalue = Rc::new(UpvalueObject::new(location));
+        self.heap.track(HeapObject::Upvalue(upvalue.clone()));
+        self.open_upvalues.push(upvalue.clone());
+        upvalue
+    }
+
+    fn close_upvalues(&mut self, last: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| {
+            if upvalue.location < last {
+                return true;
+            }
+            upvalue
+                .closed
+                .replace(Some(stack[upvalue.location].clone()));
+            false
+        });
+    }
+
+    fn bind_method(&mut self, structt: Rc<StructObject>, name: &str) -> Result<(), InterpretError> {
+        if let Some(method) = structt.find_method(name) {
             let receiver = self.peek(0);
-            let bound = BoundMethodObject::new(receiver, method.clone());
+            let bound = BoundMethodObject::new(receiver, method);
+            let bound = self.track(Value::BoundMethod(Rc::new(bound)));
             self.stack.pop();
-            self.stack.push(Value::BoundMethod(Rc::new(bound)));
-            true
+            self.stack.push(bound);
+            Ok(())
         } else {
-            let _ = self.runtime_error(&format!("Undefined property '{}'.", name));
-            false
+            self.runtime_error(&format!("Undefined property '{}'.", name))
         }
     }
 
-    fn invoke(&mut self, name: &str, arg_count: usize) -> bool {
-        if let Value::Instance(instance) = self.peek(arg_count) {
-            if let Some(value) = instance.fields.borrow().get(name) {
-                let index = self.stack.len() - arg_count as usize - 1;
+    fn invoke(&mut self, name: &str, arg_count: usize) -> Result<(), InterpretError> {
+        if let Value::Module(module) = self.peek(arg_count) {
+            let value = self.module_export(&module, name)?;
+            let index = self.stack.len() - arg_count - 1;
+            self.stack[index] = value.clone();
+            self.call_value(value, arg_count as u8)
+        } else if let Value::Struct(r#struct) = self.peek(arg_count) {
+            let function = self.static_function(&r#struct, name)?;
+            let index = self.stack.len() - arg_count - 1;
+            self.stack[index] = Value::Closure(function.clone());
+            self.call(function, arg_count as u8)
+        } else if let Value::Enum(r#enum) = self.peek(arg_count) {
+            let value = self.enum_variant(&r#enum, name)?;
+            let index = self.stack.len() - arg_count - 1;
+            self.stack[index] = value.clone();
+            self.call_value(value, arg_count as u8)
+        } else if let Value::Instance(instance) = self.peek(arg_count) {
+            let field = instance.fields.borrow().get(name).cloned();
+            if let Some(value) = field {
+                let index = self.stack.len() - arg_count - 1;
                 self.stack[index] = value.clone();
-                return self.call_value(value.clone(), arg_count as u8);
+                return self.call_value(value, arg_count as u8);
             }
             self.invoke_from_struct(instance.r#struct.clone(), name, arg_count)
         } else {
-            let _ = self.runtime_error("Only instances have methods.");
-            false
+            self.runtime_error("Only instances have methods.")
         }
     }
 
@@ -271,18 +1068,133 @@ impl VirtualMachine {
         structt: Rc<StructObject>,
         name: &str,
         arg_count: usize,
-    ) -> bool {
-        if let Some(method) = structt.methods.borrow().get(name) {
-            self.call(method.clone(), arg_count as u8)
+    ) -> Result<(), InterpretError> {
+        if let Some(method) = structt.find_method(name) {
+            self.call(method, arg_count as u8)
         } else {
-            let _ = self.runtime_error(&format!("Undefined property '{}'.", name));
-            false
+            self.runtime_error(&format!("Undefined property '{}'.", name))
         }
     }
 
+    // Lists, maps and ranges iterate themselves. A struct instance iterates
+    // the value its 'iter' method returns, or itself if it has 'next'
+    fn iterator(&mut self, iterable: Value) -> Result<Value, InterpretError> {
+        let Value::Instance(instance) = &iterable else {
+            return match iterable {
+                Value::List(_) | Value::Map(_) | Value::Range(_) => Ok(iterable),
+                _ => self.runtime_error("Only lists, maps, ranges and iterators can be iterated."),
+            };
+        };
+        let Some(iter) = instance.r#struct.find_method("iter") else {
+            if instance.r#struct.find_method("next").is_some() {
+                return Ok(iterable);
+            }
+            return self.runtime_error(&format!(
+                "{} has no 'iter' or 'next' method.",
+                instance.r#struct
+            ));
+        };
+        let iterator = self.call_method(iterable.clone(), iter)?;
+        match &iterator {
+            Value::List(_) | Value::Map(_) | Value::Range(_) => Ok(iterator),
+            Value::Instance(instance) if instance.r#struct.find_method("next").is_some() => {
+                Ok(iterator)
+            }
+            _ => self.runtime_error("'iter' must return a list, map, range or iterator."),
+        }
+    }
+
+    // Pushes the next item of an iterator, or returns false once it's done.
+    // Unpacking pushes index and item for lists, key and value for maps
+    fn push_next(
+        &mut self,
+        iterator: &Value,
+        cursor: usize,
+        unpack: bool,
+    ) -> Result<bool, InterpretError> {
+        if unpack && !matches!(iterator, Value::List(_) | Value::Map(_)) {
+            return self.runtime_error("Only lists and maps can be unpacked into two variables.");
+        }
+        let entry = match iterator {
+            Value::List(list) => list
+                .items
+                .borrow()
+                .get(cursor)
+                .map(|item| (Value::Number(cursor as f64), item.clone())),
+            Value::Map(map) => map
+                .keys
+                .borrow()
+                .get(cursor)
+                .map(|key| (key.to_value(), map.entries.borrow()[key].clone())),
+            Value::Range(range) => range
+                .nth(cursor)
+                .map(|number| (Value::None, Value::Number(number))),
+            Value::Instance(instance) => {
+                let next = instance.r#struct.find_method("next").unwrap();
+                match self.call_method(iterator.clone(), next)? {
+                    Value::None => Option::None,
+                    item => Some((Value::None, item)),
+                }
+            }
+            _ => unreachable!("for loops only iterate what Iter produced"),
+        };
+        let Some((key, item)) = entry else {
+            return Ok(false);
+        };
+        match iterator {
+            _ if unpack => self.stack.extend([key, item]),
+            Value::Map(_) => self.stack.push(key),
+            _ => self.stack.push(item),
+        }
+        Ok(true)
+    }
+
+    // Runs a method to completion, for protocols the VM drives itself
+    fn call_method(
+        &mut self,
+        receiver: Value,
+        method: Rc<ClosureObject>,
+    ) -> Result<Value, InterpretError> {
+        let exit_depth = self.frames.len();
+        self.stack.push(receiver);
+        self.call(method, 0)?;
+        self.run(exit_depth)?;
+        Ok(self.stack.pop().unwrap())
+    }
+
+    fn list_index(&mut self, list: &ListObject, index: &Value) -> Result<usize, InterpretError> {
+        if let Value::Number(index) = index {
+            if index.fract() != 0.0 {
+                return self.runtime_error("List index must be an integer.");
+            }
+            if let Some(slot) = list.resolve_index(*index) {
+                return Ok(slot);
+            }
+            let len = list.items.borrow().len();
+            self.runtime_error(&format!(
+                "List index {} out of range for list of length {}.",
+                index, len
+            ))
+        } else {
+            self.runtime_error("List index must be a number.")
+        }
+    }
+
+    fn map_key(&mut self, key: &Value) -> Result<MapKey, InterpretError> {
+        match MapKey::from_value(key) {
+            Some(key) => Ok(key),
+            Option::None => self.runtime_error("Map key must be a string, number or boolean."),
+        }
+    }
+
+    fn track(&mut self, value: Value) -> Value {
+        self.heap.track_value(&value);
+        value
+    }
+
     fn read_one_bytecode(&mut self) -> u8 {
         let ip = self.current_frame().ip;
-        let bytecode = self.current_frame().function.chunk.bytecodes[ip];
+        let bytecode = self.current_frame().closure.function.chunk.bytecodes[ip];
         self.current_frame().ip += 1;
         bytecode
     }
@@ -296,36 +1208,47 @@ impl VirtualMachine {
         ((high_byte as u16) << 8) | low_byte as u16
     }
 
-    fn read_one_constant(&mut self) -> Value {
-        let index = self.read_one_bytecode();
-        self.current_chunk()
-            .constant_pool
-            .get(index as usize)
-            .clone()
+    fn read_operand(&mut self, instruction: OpCode) -> usize {
+        if instruction.is_long() {
+            let ip = self.current_frame().ip;
+            self.current_frame().ip += 3;
+            self.current_chunk().read_long_operand(ip)
+        } else {
+            self.read_one_bytecode() as usize
+        }
+    }
+
+    fn read_constant(&mut self, instruction: OpCode) -> Value {
+        let index = self.read_operand(instruction);
+        self.current_chunk().constant_pool.get(index).clone()
     }
 
     fn binary_operator(&mut self, operator: OpCode) -> Result<(), InterpretError> {
         use OpCode::*;
-        if (self.peek(0).is_string() && self.peek(1).is_string())
-            || (self.peek(0).is_number() && self.peek(1).is_number())
-        {
-            let b = self.stack.pop().unwrap();
-            let a = self.stack.pop().unwrap();
-            let result = match operator {
-                Add => a + b,
-                Subtract => a - b,
-                Multiply => a * b,
-                Divide => a / b,
-                Greater => Value::Bool(a > b),
-                Less => Value::Bool(a < b),
-                Modulo => a.modulo(b),
-                Power => a.power(b),
-                _ => return Err(InterpretError::RuntimeError),
-            };
-            self.stack.push(result);
-            Ok(())
-        } else {
-            self.runtime_error("Operands must be two numbers or two strings.")
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+        let is_comparable = (a.is_number() && b.is_number()) || (a.is_string() && b.is_string());
+        let result = match operator {
+            Add => a + b,
+            Subtract => a - b,
+            Multiply => a * b,
+            Divide => a / b,
+            Greater | Less if !is_comparable => Err(RuntimeError::new(
+                "Operands must be two numbers or two strings.",
+            )),
+            Greater => Ok(Value::Bool(a > b)),
+            Less => Ok(Value::Bool(a < b)),
+            Modulo => a.modulo(b),
+            Power => a.power(b),
+            _ => Err(RuntimeError::new("Unknown binary operator.")),
+        };
+        match result {
+            Ok(value) => {
+                self.stack.push(value);
+                Ok(())
+            }
+            Err(error) => self.runtime_error(&error.message),
         }
     }
 
@@ -334,98 +1257,210 @@ impl VirtualMachine {
         self.stack[stack_top_index - distance].clone()
     }
 
-    fn call_value(&mut self, callee: Value, arg_count: u8) -> bool {
+    fn call_value(&mut self, callee: Value, arg_count: u8) -> Result<(), InterpretError> {
         use Value::*;
         match callee {
             Struct(class) => {
                 let index = self.stack.len() - arg_count as usize - 1;
-                let new_instance = InstanceObject::new(class.clone());
-                self.stack[index] = Value::Instance(Rc::new(new_instance));
-                if let Some(initializer) = class.methods.borrow().get("new") {
-                    self.call(initializer.clone(), arg_count);
+                let new_instance = Rc::new(InstanceObject::new(class.clone()));
+                self.stack[index] = self.track(Value::Instance(new_instance.clone()));
+                self.evaluate_defaults(&new_instance, &[])?;
+                if let Some(initializer) = class.find_method("new") {
+                    return self.call(initializer, arg_count);
                 } else if arg_count != 0 {
-                    let _ =
-                        self.runtime_error(&format!("Expected 0 arguments but got {}.", arg_count));
-                    return false;
+                    return self
+                        .runtime_error(&format!("Expected 0 arguments but got {}.", arg_count));
                 }
-                return true;
+                return Ok(());
             }
             BoundMethod(bound) => {
                 let index = self.stack.len() - arg_count as usize - 1;
                 self.stack[index] = bound.receiver.clone();
                 return self.call(bound.method.clone(), arg_count);
             }
-            Function(function) => return self.call(function.clone(), arg_count),
+            Closure(closure) => return self.call(closure.clone(), arg_count),
             NativeFunction(function) => {
+                if !function.arity().accepts(arg_count as usize) {
+                    return self.runtime_error(&format!(
+                        "Expected {} arguments but got {}.",
+                        function.arity(),
+                        arg_count
+                    ));
+                }
+
                 let stack_top = self.stack.len();
                 let result = function.call(
                     arg_count as usize,
                     &self.stack[stack_top - arg_count as usize..stack_top],
                 );
-                self.stack.truncate(stack_top - (arg_count + 1) as usize);
-                self.stack.push(result);
-                return true;
+                match result {
+                    Ok(result) => {
+                        self.heap.track_value(&result);
+                        self.stack.truncate(stack_top - (arg_count + 1) as usize);
+                        self.stack.push(result);
+                        return Ok(());
+                    }
+                    Err(error) => return self.runtime_error(&error.message),
+                }
             }
             _ => (),
         }
-        let _ = self.runtime_error("Can only call functions and structs.");
-        false
+        self.runtime_error("Can only call functions and structs.")
+    }
+
+    // Fills in a struct literal. Fields left out take their defaults, so
+    // only declared fields without one have to be given
+    fn build_instance(
+        &mut self,
+        r#struct: Rc<StructObject>,
+        entries: Vec<Value>,
+    ) -> Result<Value, InterpretError> {
+        let instance = Rc::new(InstanceObject::new(r#struct.clone()));
+        let mut given = Vec::new();
+        for entry in entries.chunks(2) {
+            let Value::String(name) = &entry[0] else {
+                unreachable!("struct literal fields are always named");
+            };
+            if !r#struct.accepts_field(name) {
+                return self.runtime_error(&format!("{} has no field '{}'.", r#struct, name));
+            }
+            instance
+                .fields
+                .borrow_mut()
+                .insert(name.clone(), entry[1].clone());
+            given.push(name.clone());
+        }
+        let missing = r#struct
+            .fields
+            .borrow()
+            .iter()
+            .find(|field| field.default.is_none() && !given.contains(&field.name))
+            .map(|field| field.name.clone());
+        if let Some(missing) = missing {
+            return self.runtime_error(&format!(
+                "Missing field '{}' in {} literal.",
+                missing, r#struct
+            ));
+        }
+        let value = self.track(Value::Instance(instance.clone()));
+        self.stack.push(value);
+        self.evaluate_defaults(&instance, &given)?;
+        Ok(self.stack.pop().unwrap())
+    }
+
+    // Runs the default of each field not given, for a new instance kept on
+    // the stack meanwhile
+    fn evaluate_defaults(
+        &mut self,
+        instance: &InstanceObject,
+        given: &[Rc<str>],
+    ) -> Result<(), InterpretError> {
+        let fields = instance.r#struct.fields.borrow().clone();
+        for field in fields {
+            let Some(default) = field.default else {
+                continue;
+            };
+            if given.contains(&field.name) {
+                continue;
+            }
+            let value = self.call_method(Value::Closure(default.clone()), default)?;
+            instance.fields.borrow_mut().insert(field.name, value);
+        }
+        Ok(())
     }
 
-    fn define_method(&mut self, name: String) {
-        if let Value::Function(method) = self.peek(0) {
+    fn define_method(&mut self, name: Rc<str>) {
+        if let Value::Closure(method) = self.peek(0) {
             if let Value::Struct(structt) = self.peek(1) {
-                structt
-                    .methods
-                    .borrow_mut()
-                    .insert(name.to_string(), method);
+                structt.methods.borrow_mut().insert(name, method);
                 self.stack.pop();
             }
         }
     }
 
-    fn define_native(&mut self, name: &str, function: Rc<dyn NativeFunctionObject>) {
-        self.globals
-            .insert(name.to_string(), Value::NativeFunction(function.clone()));
+    pub fn define_native(&mut self, name: &str, function: Rc<dyn NativeFunctionObject>) {
+        let name = self.interner.borrow_mut().intern(name);
+        let value = Value::NativeFunction(function.clone());
+        self.builtins.define_by_name(name.clone(), value.clone());
+        self.globals.borrow_mut().define_by_name(name, value);
     }
 
-    fn call(&mut self, function: Rc<FunctionObject>, arg_count: u8) -> bool {
-        if arg_count as usize != function.arity {
-            let _ = self.runtime_error(&format!(
+    fn call(&mut self, closure: Rc<ClosureObject>, arg_count: u8) -> Result<(), InterpretError> {
+        if arg_count as usize != closure.function.arity {
+            return self.runtime_error(&format!(
                 "Expected {} arguments but got {}.",
-                function.arity, arg_count
+                closure.function.arity, arg_count
             ));
-            return false;
         }
 
         if self.frames.len() == u8::MAX.into() {
-            let _ = self.runtime_error("Stack overflow.");
-            return false;
+            return self.runtime_error("Stack overflow.");
         }
 
         let base_slot = self.stack.len() - arg_count as usize - 1;
         self.frames.push(CallFrame {
-            function: function.clone(),
+            closure,
             ip: 0,
             base_slot,
         });
-        true
-    }
-
-    fn runtime_error(&mut self, message: &str) -> Result<(), InterpretError> {
-        eprintln!("{}", message);
-        for frame in self.frames.iter().rev() {
-            let index = frame.ip - 1;
-            let line_number = frame.function.chunk.line_numbers[index];
-            eprint!("[line {}] in ", line_number);
-            if frame.function.name.is_empty() {
-                eprintln!("script");
-            } else {
-                eprintln!("{}()", frame.function.name);
+        Ok(())
+    }
+
+    fn runtime_error<T>(&mut self, message: &str) -> Result<T, InterpretError> {
+        Err(InterpretError::RuntimeError(RuntimeError {
+            message: message.to_string(),
+            trace: self.trace(),
+            value: Option::None,
+        }))
+    }
+
+    // Errors thrown as Error values pick up the trace where they're first thrown
+    fn throw<T>(&mut self, value: Value) -> Result<T, InterpretError> {
+        let trace = self.trace();
+        let message = match &value {
+            Value::Instance(instance) if Rc::ptr_eq(&instance.r#struct, &self.error_struct) => {
+                let mut fields = instance.fields.borrow_mut();
+                let is_untraced = match fields.get("trace") {
+                    Some(Value::List(list)) => list.items.borrow().is_empty(),
+                    _ => true,
+                };
+                if is_untraced {
+                    fields.insert("trace".into(), trace_value(&trace));
+                }
+                fields
+                    .get("message")
+                    .map_or_else(String::new, ToString::to_string)
             }
-        }
+            value => format!("Uncaught exception: {}", value),
+        };
+        Err(InterpretError::RuntimeError(RuntimeError {
+            message,
+            trace,
+            value: Some(value),
+        }))
+    }
+
+    fn trace(&self) -> Vec<TraceFrame> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| {
+                let index = frame.ip - 1;
+                let function = &frame.closure.function;
+                TraceFrame {
+                    function: function.name.clone(),
+                    line_number: function.chunk.line_numbers[index],
+                    span: function.chunk.spans[index],
+                    source: function.chunk.source.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn reset(&mut self) {
         self.stack = Vec::new();
         self.frames = Vec::new();
-        Err(InterpretError::RuntimeError)
+        self.open_upvalues = Vec::new();
+        self.handlers = Vec::new();
     }
 }
@@ -0,0 +1,97 @@
+use crate::{
+    diagnostic::{Diagnostic, Span},
+    object::{instance_object::InstanceObject, struct_object::StructObject},
+    value::Value,
+};
+use std::{
+    error::Error,
+    fmt::{Display, Write},
+    rc::Rc,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    // Innermost call first
+    pub trace: Vec<TraceFrame>,
+    // What the script threw, if the error didn't come from the VM itself
+    pub value: Option<Value>,
+}
+
+impl RuntimeError {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            trace: Vec::new(),
+            value: None,
+        }
+    }
+
+    // The message with the failing expression underlined, then the stack trace
+    pub fn render(&self) -> String {
+        let mut output = match self.trace.first() {
+            Some(TraceFrame {
+                source: Some(source),
+                span,
+                ..
+            }) => Diagnostic::error(*span, &self.message).render(source),
+            _ => format!("{}\n", self.message),
+        };
+        for frame in &self.trace {
+            let _ = writeln!(output, "{}", frame);
+        }
+        output
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for RuntimeError {}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceFrame {
+    // Empty for top-level script code
+    pub function: String,
+    pub line_number: usize,
+    pub span: Span,
+    pub source: Option<Rc<str>>,
+}
+
+// Scripts catch errors as instances of the built-in Error struct
+pub fn error_instance(
+    error_struct: &Rc<StructObject>,
+    message: &str,
+    trace: &[TraceFrame],
+) -> Value {
+    let instance = InstanceObject::new(error_struct.clone());
+    instance.fields.borrow_mut().extend([
+        ("message".into(), Value::from(message)),
+        ("trace".into(), trace_value(trace)),
+    ]);
+    Value::Instance(Rc::new(instance))
+}
+
+// One "[line N] in f()" string per frame
+pub fn trace_value(trace: &[TraceFrame]) -> Value {
+    let frames: Vec<Value> = trace
+        .iter()
+        .map(|frame| Value::from(frame.to_string()))
+        .collect();
+    Value::from(frames)
+}
+
+impl Display for TraceFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.function.is_empty() {
+            write!(f, "[line {}] in script", self.line_number)
+        } else if self.function.contains('@') {
+            write!(f, "[line {}] in <fn {}>", self.line_number, self.function)
+        } else {
+            write!(f, "[line {}] in {}()", self.line_number, self.function)
+        }
+    }
+}
@@ -0,0 +1,1414 @@
+use super::*;
+use std::{
+    fmt::Debug,
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+fn runtime_message<T: Debug>(result: Result<T, InterpretError>) -> String {
+    match result {
+        Err(InterpretError::RuntimeError(error)) => error.message,
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
+
+fn compile_messages<T: Debug>(result: Result<T, InterpretError>) -> Vec<String> {
+    match result {
+        Err(InterpretError::CompileError(diagnostics)) => diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.diagnostic.message)
+            .collect(),
+        other => panic!("expected a compile error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_closures() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        fn counter() {
+            let count = 0;
+            fn increment() { count = count + 1; return count; }
+            return increment;
+        }
+        let a = counter();
+        let b = counter();
+        a();
+        a();
+        let counts = [a(), b()];
+
+        fn pair() {
+            let value = "before";
+            fn get() { return value; }
+            fn set(v) { value = v; }
+            value = "changed";
+            return [get, set];
+        }
+        let accessors = pair();
+        let changed = accessors[0]();
+        accessors[1]("set");
+        let set = accessors[0]();
+
+        fn outer() {
+            let x = "outer";
+            fn middle() {
+                fn inner() { return x; }
+                return inner;
+            }
+            return middle;
+        }
+        let nested = outer()()();
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Ok(Value::Bool(true)), vm.eval("counts == [3, 1];"));
+    assert_eq!(Some(Value::from("changed")), vm.get_global("changed"));
+    assert_eq!(Some(Value::from("set")), vm.get_global("set"));
+    assert_eq!(Some(Value::from("outer")), vm.get_global("nested"));
+}
+
+#[test]
+fn test_super() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        struct A {
+            fn new(name) { self.name = name; }
+            fn describe() { return "A " + self.name; }
+            fn kind() { return "a"; }
+        }
+        struct B: A {
+            fn new(name) { super.new(name + "!"); }
+            fn describe() { return "B then " + super.describe(); }
+        }
+        struct C: B {
+            fn describe() { let parent = super.describe; return "C then " + parent(); }
+            fn kind() { return "c after " + super.kind(); }
+        }
+        let c = C("x");
+        let described = c.describe();
+        let kind = c.kind();
+        let inherited = B("y").kind();
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(
+        Some(Value::from("C then B then A x!")),
+        vm.get_global("described")
+    );
+    assert_eq!(Some(Value::from("c after a")), vm.get_global("kind"));
+    assert_eq!(Some(Value::from("a")), vm.get_global("inherited"));
+    assert_eq!(
+        "Undefined property 'missing'.",
+        runtime_message(
+            vm.interpret("struct D: A { fn f() { return super.missing(); } } D(\"d\").f();")
+        )
+    );
+    assert_eq!(
+        vec![
+            "Can't use 'super' outside of a struct.",
+            "Can't use 'super' in a struct with no parent."
+        ],
+        compile_messages(vm.interpret("super.f();\nstruct E { fn f() { super.f(); } }"))
+    );
+    assert_eq!(
+        vec!["A struct can't inherit from itself."],
+        compile_messages(vm.interpret("struct F: F {}"))
+    );
+}
+
+#[test]
+fn test_lists() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let xs = [1, "two", [3]];
+        let first = xs[0];
+        let last = xs[-1][0];
+        xs[1] = 2;
+        xs[-1] = 3;
+        let sum = xs[0] + xs[1] + xs[2];
+        let empty = [];
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(1.0)), vm.get_global("first"));
+    assert_eq!(Some(Value::Number(3.0)), vm.get_global("last"));
+    assert_eq!(Some(Value::Number(6.0)), vm.get_global("sum"));
+    assert_eq!(Ok(Value::Bool(true)), vm.eval("xs == [1, 2, 3];"));
+    assert_eq!(
+        Ok(Value::from("[1, 2, 3] []")),
+        vm.eval("\"${xs} ${empty}\";")
+    );
+    assert_eq!(
+        "List index 3 out of range for list of length 3.",
+        runtime_message(vm.interpret("xs[3];"))
+    );
+    assert_eq!(
+        "List index -4 out of range for list of length 3.",
+        runtime_message(vm.interpret("xs[-4] = 0;"))
+    );
+    assert_eq!(
+        "List index must be an integer.",
+        runtime_message(vm.interpret("xs[0.5];"))
+    );
+}
+
+#[test]
+fn test_maps() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let m = {"a": 1, 2: "two", true: [3]};
+        let a = m["a"];
+        let two = m[2];
+        let three = m[true][0];
+        m["a"] = 10;
+        m[-0] = "zero";
+        m[false] = "no";
+        let zero = m[0];
+        let empty = {};
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(1.0)), vm.get_global("a"));
+    assert_eq!(Some(Value::from("two")), vm.get_global("two"));
+    assert_eq!(Some(Value::Number(3.0)), vm.get_global("three"));
+    assert_eq!(Some(Value::from("zero")), vm.get_global("zero"));
+    assert_eq!(
+        Ok(Value::Bool(true)),
+        vm.eval("keys(m) == [\"a\", 2, true, 0, false];")
+    );
+    assert_eq!(
+        Ok(Value::Bool(true)),
+        vm.eval("m[2.0] == \"two\" and m[1 + 1] == m[2];")
+    );
+    assert_eq!(
+        Ok(Value::from(
+            "{a: 10, 2: two, true: [3], 0: zero, false: no} {}"
+        )),
+        vm.eval("\"${m} ${empty}\";")
+    );
+    assert_eq!(
+        Ok(Value::Bool(true)),
+        vm.eval("({1: 2} == {1: 2}) and {1: 2} != {1: 3};")
+    );
+    assert_eq!(
+        "Undefined key 'b'.",
+        runtime_message(vm.interpret("m[\"b\"];"))
+    );
+    assert_eq!(
+        "Map key must be a string, number or boolean.",
+        runtime_message(vm.interpret("m[[1]] = 1;"))
+    );
+    assert_eq!(
+        "Map key must be a string, number or boolean.",
+        runtime_message(vm.interpret("m[none];"))
+    );
+}
+
+#[test]
+fn test_type_errors() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(
+        "Operands must be two numbers or two strings.",
+        runtime_message(vm.interpret("1 + \"a\";"))
+    );
+    assert_eq!(
+        "Operands must be two numbers.",
+        runtime_message(vm.interpret("\"a\" - 1;"))
+    );
+    assert_eq!(
+        "Operand must be a number.",
+        runtime_message(vm.interpret("-\"a\";"))
+    );
+    assert_eq!(
+        "Can't convert 'abc' to a number.",
+        runtime_message(vm.interpret("Number(\"abc\");"))
+    );
+    assert_eq!(
+        "Can't convert true to a number.",
+        runtime_message(vm.interpret("Number(true);"))
+    );
+    assert_eq!(
+        "Can't convert none to a number.",
+        runtime_message(vm.interpret("Number(none);"))
+    );
+    assert_eq!(Ok(Value::Number(1.5)), vm.eval("Number(\" 1.5 \");"));
+
+    // The error carries a trace, and the VM stays usable afterwards
+    let error = vm.interpret("fn f() { return none * 2; }\nf();");
+    let Err(InterpretError::RuntimeError(error)) = error else {
+        panic!("expected a runtime error, got {:?}", error);
+    };
+    assert_eq!("Operands must be two numbers.", error.message);
+    let functions = error
+        .trace
+        .iter()
+        .map(|frame| frame.function.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(vec!["f", ""], functions);
+    assert_eq!(Ok(Value::Number(3.0)), vm.eval("1 + 2;"));
+}
+
+#[test]
+fn test_native_functions() {
+    let mut vm = VirtualMachine::new();
+    vm.register_fn("pick", Arity::Range(1, 2), |args| {
+        Ok(args.get(1).unwrap_or(&args[0]).clone())
+    });
+    vm.register_fn("count", Arity::Variadic(1), |args| {
+        Ok(Value::Number(args.len() as f64))
+    });
+    assert_eq!(
+        Ok(Value::from(
+            "<native fn clock> <native fn Number> <native fn pick>"
+        )),
+        vm.eval("\"${clock} ${Number} ${pick}\";")
+    );
+    assert_eq!(Ok(Value::Number(2.0)), vm.eval("pick(1, 2);"));
+    assert_eq!(Ok(Value::Number(3.0)), vm.eval("count(1, 2, 3);"));
+    assert_eq!(
+        "Expected 0 arguments but got 1.",
+        runtime_message(vm.interpret("clock(1);"))
+    );
+    assert_eq!(
+        "Expected 1 arguments but got 0.",
+        runtime_message(vm.interpret("String();"))
+    );
+    assert_eq!(
+        "Expected 1 arguments but got 2.",
+        runtime_message(vm.interpret("Number(\"ff\", 16);"))
+    );
+    assert_eq!(
+        "Expected 1 to 2 arguments but got 3.",
+        runtime_message(vm.interpret("pick(1, 2, 3);"))
+    );
+    assert_eq!(
+        "Expected at least 1 arguments but got 0.",
+        runtime_message(vm.interpret("count();"))
+    );
+}
+
+#[test]
+fn test_cyclic_lists() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let ys = [1];
+        ys[0] = ys;
+        let zs = [1];
+        zs[0] = zs;
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Ok(Value::from("[[...]]")), vm.eval("\"${ys}\";"));
+    assert_eq!(Ok(Value::Bool(true)), vm.eval("ys == zs;"));
+    assert_eq!(Ok(Value::Bool(false)), vm.eval("ys == [ys, 1];"));
+}
+
+#[test]
+fn test_cyclic_maps() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let m = {"n": 1};
+        m["self"] = m;
+        m["list"] = [m];
+        let other = {"n": 1};
+        other["self"] = other;
+        other["list"] = [other];
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(
+        Ok(Value::from("{n: 1, self: {...}, list: [{...}]}")),
+        vm.eval("\"${m}\";")
+    );
+    assert_eq!(Ok(Value::Bool(true)), vm.eval("m == other;"));
+    assert_eq!(
+        Ok(Value::Bool(false)),
+        vm.eval("other[\"n\"] = 2; m == other;")
+    );
+}
+
+#[test]
+fn test_interpret_defines_globals() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(Ok(()), vm.interpret("let a = 1 + 2;"));
+    assert_eq!(Some(Value::Number(3.0)), vm.get_global("a"));
+    assert_eq!(None, vm.get_global("b"));
+}
+
+#[test]
+fn test_set_global() {
+    let mut vm = VirtualMachine::new();
+    vm.set_global("name", Value::from("world"));
+    assert_eq!(Ok(()), vm.interpret("let greeting = \"hello \" + name;"));
+    assert_eq!(Some(Value::from("hello world")), vm.get_global("greeting"));
+}
+
+#[test]
+fn test_register_fn() {
+    let mut vm = VirtualMachine::new();
+    vm.register_fn("double", Arity::Exact(1), |args| match &args[0] {
+        Value::Number(n) => Ok(Value::Number(n * 2.0)),
+        _ => Err(RuntimeError::new("double() expects a number.")),
+    });
+    assert_eq!(Ok(()), vm.interpret("let a = double(21);"));
+    assert_eq!(Some(Value::Number(42.0)), vm.get_global("a"));
+    assert_eq!(
+        "double() expects a number.",
+        runtime_message(vm.interpret("double(\"x\");"))
+    );
+    assert_eq!(
+        "Expected 1 arguments but got 0.",
+        runtime_message(vm.interpret("double();"))
+    );
+}
+
+#[test]
+fn test_register_fn_captures_host_state() {
+    let mut vm = VirtualMachine::new();
+    let calls = Rc::new(std::cell::Cell::new(0));
+    let counter = calls.clone();
+    vm.register_fn("tick", Arity::Exact(0), move |_| {
+        counter.set(counter.get() + 1);
+        Ok(Value::None)
+    });
+    assert_eq!(Ok(()), vm.interpret("tick(); tick(); tick();"));
+    assert_eq!(3, calls.get());
+}
+
+#[test]
+fn test_call_global() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(
+        Ok(()),
+        vm.interpret("fn add(a, b) { return a + b; } let base = 10;")
+    );
+    let result = vm.call_global("add", &[Value::from(1.0), Value::from(2.0)]);
+    assert_eq!(Ok(Value::Number(3.0)), result);
+    assert_eq!(Some(Value::Number(10.0)), vm.get_global("base"));
+}
+
+#[test]
+fn test_call_closure_and_native() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(
+        Ok(()),
+        vm.interpret(
+            "fn counter() { let n = 0; fn next() { n = n + 1; return n; } return next; }
+             let next = counter();"
+        )
+    );
+    let next = vm.get_global("next").unwrap();
+    assert_eq!(Ok(Value::Number(1.0)), vm.call_function(&next, &[]));
+    assert_eq!(Ok(Value::Number(2.0)), vm.call_function(&next, &[]));
+    assert_eq!(
+        Ok(Value::from("12")),
+        vm.call_global("String", &[Value::from(12.0)])
+    );
+}
+
+#[test]
+fn test_call_struct_constructor() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(
+        Ok(()),
+        vm.interpret("struct Point { fn new(x) { self.x = x; } fn get() { return self.x; } }")
+    );
+    let point = vm.call_global("Point", &[Value::from(5.0)]).unwrap();
+    vm.set_global("p", point);
+    assert_eq!(Ok(()), vm.interpret("let x = p.get();"));
+    assert_eq!(Some(Value::Number(5.0)), vm.get_global("x"));
+}
+
+#[test]
+fn test_call_errors() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(Ok(()), vm.interpret("fn f(a) { return -a; }"));
+    assert_eq!(
+        "Undefined variable 'missing'",
+        runtime_message(vm.call_global("missing", &[]))
+    );
+    assert_eq!(
+        "Expected 1 arguments but got 0.",
+        runtime_message(vm.call_global("f", &[]))
+    );
+    assert_eq!(
+        "Operand must be a number.",
+        runtime_message(vm.call_global("f", &[Value::from("x")]))
+    );
+    assert_eq!(
+        Ok(Value::Number(-1.0)),
+        vm.call_global("f", &[Value::from(1.0)])
+    );
+}
+
+#[test]
+fn test_collect_unreachable_cycles() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(
+        Ok(()),
+        vm.interpret(
+            "struct Node {}
+             fn link() { let a = Node(); let b = Node(); a.other = b; b.other = a; }
+             link(); link();"
+        )
+    );
+    assert!(vm.collect_garbage() >= 4);
+    assert_eq!(2, vm.heap_stats().objects);
+    assert_eq!(
+        Ok(()),
+        vm.interpret("let n = Node(); n.me = n; let x = n.me == n;")
+    );
+    assert_eq!(Some(Value::Bool(true)), vm.get_global("x"));
+}
+
+#[test]
+fn test_collect_keeps_reachable_cycles() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(
+        Ok(()),
+        vm.interpret("let l = [1]; l[0] = l; let m = {}; m[\"m\"] = m;")
+    );
+    vm.collect_garbage();
+    assert_eq!(
+        Ok(()),
+        vm.interpret("let ok = l[0][0] == l and m[\"m\"][\"m\"] == m;")
+    );
+    assert_eq!(Some(Value::Bool(true)), vm.get_global("ok"));
+}
+
+#[test]
+fn test_collect_keeps_host_values() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(
+        Ok(()),
+        vm.interpret(
+            "struct Node { fn new() { self.me = self; self.n = 7; } }
+             fn counter() { let n = 0; fn next() { n = n + 1; return n; } return next; }"
+        )
+    );
+    let node = vm.call_global("Node", &[]).unwrap();
+    let next = vm.call_global("counter", &[]).unwrap();
+    assert_eq!(Ok(Value::Number(1.0)), vm.call_function(&next, &[]));
+    vm.collect_garbage();
+    assert_eq!(Ok(Value::Number(2.0)), vm.call_function(&next, &[]));
+    vm.set_global("node", node);
+    assert_eq!(Ok(()), vm.interpret("let n = node.me.n;"));
+    assert_eq!(Some(Value::Number(7.0)), vm.get_global("n"));
+}
+
+#[test]
+fn test_collect_on_threshold() {
+    let mut vm = VirtualMachine::new();
+    vm.set_gc_threshold(16);
+    assert_eq!(
+        Ok(()),
+        vm.interpret(
+            "let i = 0;
+             while (i < 100) { let l = [i]; l[0] = l; i = i + 1; }"
+        )
+    );
+    let stats = vm.heap_stats();
+    assert!(stats.collections > 0);
+    assert!(stats.freed >= 90);
+    assert!(stats.objects <= 32);
+}
+
+// A temporary directory of module files, removed when the test ends
+struct ModuleDir(PathBuf);
+
+impl Deref for ModuleDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ModuleDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn module_dir(name: &str, files: &[(&str, &str)]) -> ModuleDir {
+    let dir = std::env::temp_dir().join(format!("rustscript-{}-{}", name, std::process::id()));
+    for (path, source) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    ModuleDir(dir)
+}
+
+#[test]
+fn test_import_module_namespace() {
+    let dir = module_dir(
+        "namespace",
+        &[
+            ("main.rs", ""),
+            (
+                "lib/math.rs",
+                "import \"util.rs\"; let pi = 3; fn square(x) { return util.double(x) * x / 2; }",
+            ),
+            ("lib/util.rs", "fn double(x) { return x * 2; }"),
+        ],
+    );
+    let mut vm = VirtualMachine::new();
+    let source = "import \"lib/math.rs\"; import \"lib/math.rs\" as m;
+                  let a = math.square(4); let b = m.pi; let same = math == m;";
+    assert_eq!(Ok(()), vm.interpret_with_path(source, dir.join("main.rs")));
+    assert_eq!(Some(Value::Number(16.0)), vm.get_global("a"));
+    assert_eq!(Some(Value::Number(3.0)), vm.get_global("b"));
+    assert_eq!(Some(Value::Bool(true)), vm.get_global("same"));
+    assert_eq!(None, vm.get_global("pi"));
+    assert_eq!(None, vm.get_global("util"));
+}
+
+#[test]
+fn test_import_runs_module_once() {
+    let dir = module_dir(
+        "once",
+        &[("main.rs", ""), ("counter.rs", "tick(); let n = 1;")],
+    );
+    let mut vm = VirtualMachine::new();
+    let calls = Rc::new(std::cell::Cell::new(0));
+    let counter = calls.clone();
+    vm.register_fn("tick", Arity::Exact(0), move |_| {
+        counter.set(counter.get() + 1);
+        Ok(Value::None)
+    });
+    let source =
+        "import \"counter.rs\"; fn f() { import \"counter.rs\" as c; return c.n; } let n = f();";
+    assert_eq!(Ok(()), vm.interpret_with_path(source, dir.join("main.rs")));
+    assert_eq!(1, calls.get());
+    assert_eq!(Some(Value::Number(1.0)), vm.get_global("n"));
+}
+
+#[test]
+fn test_collect_keeps_module_globals() {
+    let dir = module_dir(
+        "collect",
+        &[
+            ("main.rs", ""),
+            (
+                "cache.rs",
+                "let items = [1]; items[0] = items; fn first() { return items[0]; }",
+            ),
+        ],
+    );
+    let mut vm = VirtualMachine::new();
+    let source = "import \"cache.rs\"; let first = cache.first; let module = cache;";
+    assert_eq!(Ok(()), vm.interpret_with_path(source, dir.join("main.rs")));
+    vm.collect_garbage();
+    assert_eq!(
+        Ok(Value::Bool(true)),
+        vm.eval("first()[0][0] == module.items;")
+    );
+}
+
+#[test]
+fn test_import_sees_only_builtins() {
+    let dir = module_dir(
+        "builtins",
+        &[
+            ("main.rs", ""),
+            ("reads.rs", "let seen = secret;"),
+            ("writes.rs", "fn f() { secret = 2; } f();"),
+            ("shadows.rs", "fn f() { clock = 2; } f();"),
+            ("natives.rs", "let text = String(1); let host = tick();"),
+        ],
+    );
+    let main = dir.join("main.rs");
+    let mut vm = VirtualMachine::new();
+    vm.register_fn("tick", Arity::Exact(0), |_| Ok(Value::Number(7.0)));
+    assert_eq!(Ok(()), vm.interpret("let secret = 1;"));
+    for (source, message) in [
+        ("import \"reads.rs\";", "Undefined variable 'secret'"),
+        ("import \"writes.rs\";", "Undefined variable 'secret'"),
+        ("import \"shadows.rs\";", "Undefined variable 'clock'"),
+    ] {
+        assert_eq!(
+            message,
+            runtime_message(vm.interpret_with_path(source, &main))
+        );
+    }
+    assert_eq!(Some(Value::Number(1.0)), vm.get_global("secret"));
+    let source = "import \"natives.rs\"; let text = natives.text; let host = natives.host;";
+    assert_eq!(Ok(()), vm.interpret_with_path(source, &main));
+    assert_eq!(Some(Value::from("1")), vm.get_global("text"));
+    assert_eq!(Some(Value::Number(7.0)), vm.get_global("host"));
+}
+
+#[test]
+fn test_import_errors() {
+    let dir = module_dir(
+        "errors",
+        &[
+            ("a.rs", "import \"b.rs\";"),
+            ("b.rs", "import \"a.rs\";"),
+            ("broken.rs", "let = 1;"),
+            ("small.rs", "let x = 1;"),
+        ],
+    );
+    let main = dir.join("main.rs");
+    let mut vm = VirtualMachine::new();
+    for (source, message) in [
+        ("import \"a.rs\";", "Cyclic import: a.rs -> b.rs -> a.rs."),
+        ("import \"missing.rs\";", "Could not import 'missing.rs'"),
+        (
+            "import \"broken.rs\";",
+            "Could not compile module 'broken.rs':\n[line 1:5] error: Expect variable name.",
+        ),
+        (
+            "import \"small.rs\"; small.y;",
+            "Undefined name 'y' in module 'small'.",
+        ),
+    ] {
+        let error = runtime_message(vm.interpret_with_path(source, &main));
+        assert!(error.starts_with(message), "{error}");
+    }
+    assert_eq!(
+        vec!["Module name 'my-module' is not a valid identifier."],
+        compile_messages(vm.interpret("import \"my-module.rs\";"))
+    );
+}
+
+#[test]
+fn test_string_escapes() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"let a = "tab\tquote\"slash\\ \u{e9}"; let b = r"C:\n"; let c = "one \
+                   two";"#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(
+        Some(Value::from("tab\tquote\"slash\\ \u{e9}")),
+        vm.get_global("a")
+    );
+    assert_eq!(Some(Value::from("C:\\n")), vm.get_global("b"));
+    assert_eq!(Some(Value::from("one two")), vm.get_global("c"));
+}
+
+#[test]
+fn test_string_interpolation() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"struct P {} let name = "bob"; let age = 41;
+                    let a = "hello ${name}, you are ${age + 1}";
+                    let b = "${[1, "x"]} ${ "inner ${name}" } ${P()} \${age}";"#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(
+        Some(Value::from("hello bob, you are 42")),
+        vm.get_global("a")
+    );
+    assert_eq!(
+        Some(Value::from("[1, x] inner bob P instance ${age}")),
+        vm.get_global("b")
+    );
+    assert_eq!(
+        vec!["Expect end of string interpolation."],
+        compile_messages(vm.interpret("let c = \"${1 2}\";"))
+    );
+}
+
+#[test]
+fn test_wide_constant_operands() {
+    let mut vm = VirtualMachine::new();
+    let mut source: String = (0..300).map(|i| format!("let v{i} = {i}.5;\n")).collect();
+    source.push_str(
+        "struct S { fn get() { return v299; } }
+         fn outer() { let x = 1; fn inner() { return x + v298; } return inner; }
+         let s = S(); s.field = v1;
+         let total = s.field + s.get() + outer()();",
+    );
+    assert_eq!(Ok(()), vm.interpret(&source));
+    assert_eq!(Some(Value::Number(0.5)), vm.get_global("v0"));
+    assert_eq!(Some(Value::Number(299.5)), vm.get_global("v299"));
+    assert_eq!(Some(Value::Number(600.5)), vm.get_global("total"));
+}
+
+#[test]
+fn test_constants_are_deduplicated() {
+    let interner = Rc::new(RefCell::new(Interner::new()));
+    let globals = Rc::new(RefCell::new(Globals::new()));
+    let compiler = Compiler::new(FunctionKind::Script, interner.clone(), globals);
+    let function = compiler
+        .compile("let x = 1; x = x + 1; print \"x\"; print \"x\" + \"y\";")
+        .unwrap();
+    let constants = &function.chunk.constant_pool.0;
+    assert_eq!(3, constants.len());
+    assert_eq!(2, interner.borrow().len());
+}
+
+#[test]
+fn test_strings_are_interned() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(Ok(()), vm.interpret("let a = \"shared\";"));
+    assert_eq!(
+        Ok(()),
+        vm.interpret("let b = \"shared\"; let c = a + \"\";")
+    );
+    let (Some(Value::String(a)), Some(Value::String(b)), Some(Value::String(c))) =
+        (vm.get_global("a"), vm.get_global("b"), vm.get_global("c"))
+    else {
+        panic!("expected strings");
+    };
+    assert!(Rc::ptr_eq(&a, &b));
+    assert!(!Rc::ptr_eq(&a, &c));
+    assert_eq!(Value::String(a), Value::String(c));
+}
+
+#[test]
+fn test_global_slots() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(
+        "Undefined variable 'later'",
+        runtime_message(vm.interpret("print later;"))
+    );
+    assert_eq!(
+        "Undefined variable 'later'",
+        runtime_message(vm.interpret("later = 1;"))
+    );
+    assert_eq!(None, vm.get_global("later"));
+    assert_eq!(
+        Ok(()),
+        vm.interpret("fn f() { return g() + later; } fn g() { return 1; } let later = 2;")
+    );
+    assert_eq!(Ok(()), vm.interpret("later = later + 1; let r = f();"));
+    assert_eq!(Some(Value::Number(4.0)), vm.get_global("r"));
+    vm.set_global("later", Value::Number(10.0));
+    assert_eq!(Ok(Value::Number(11.0)), vm.call_global("f", &[]));
+}
+
+#[test]
+fn test_eval_returns_last_expression() {
+    let mut vm = VirtualMachine::new();
+    assert_eq!(Ok(Value::Number(3.0)), vm.eval("let a = 1; a + 2;"));
+    assert_eq!(Ok(Value::None), vm.eval("let b = a;"));
+    assert_eq!(Ok(Value::None), vm.eval("a; print a;"));
+    assert_eq!(Ok(Value::None), vm.eval("if (a == 1) a;"));
+    assert_eq!(Ok(Value::None), vm.eval("{ a; }"));
+    assert_eq!(
+        "Undefined variable 'missing'",
+        runtime_message(vm.eval("missing;"))
+    );
+    assert_eq!(Ok(Value::from("kept")), vm.eval("a = \"kept\"; a;"));
+
+    // The last statement may leave out its ';', even when it ends in a brace
+    assert_eq!(Ok(Value::Number(3.0)), vm.eval("1 + 2"));
+    assert_eq!(Ok(Value::None), vm.eval("struct P { x; }"));
+    assert!(matches!(vm.eval("P { x: 2 }"), Ok(Value::Instance(_))));
+    assert_eq!(Ok(Value::None), vm.eval("let m = {\"a\": 1}"));
+    assert_eq!(Ok(Value::Bool(true)), vm.eval("m == {\"a\": 1}"));
+    assert!(matches!(vm.eval("({\"b\": 2})"), Ok(Value::Map(_))));
+    assert_eq!(
+        vec!["Expect ';' after expression."],
+        compile_messages(vm.eval("1 2"))
+    );
+    assert_eq!(
+        vec!["Expect ';' after variable declaration."],
+        compile_messages(vm.interpret("let n = 1"))
+    );
+}
+
+#[test]
+fn test_expression_spans() {
+    let interner = Rc::new(RefCell::new(Interner::new()));
+    let globals = Rc::new(RefCell::new(Globals::new()));
+    let compiler = Compiler::new(FunctionKind::Script, interner, globals);
+    let source = "print a + b * -c;\nprint f(1, 2)[0];\np.x = p.y;";
+    let function = compiler.compile(source).unwrap();
+    let spanned = function
+        .chunk
+        .spans
+        .iter()
+        .map(|span| &source[span.start..span.end])
+        .collect::<Vec<_>>();
+    for text in [
+        "a + b * -c",
+        "b * -c",
+        "-c",
+        "f(1, 2)",
+        "f(1, 2)[0]",
+        "p.y",
+        "p.x = p.y",
+    ] {
+        assert!(spanned.contains(&text), "no bytecode spans '{text}'");
+    }
+}
+
+#[test]
+fn test_reports_every_compile_error() {
+    let mut vm = VirtualMachine::new();
+    let result = vm.interpret("print 1 +;\nlet = 2;\nlet b = ;\nprint 3;");
+    let Err(InterpretError::CompileError(diagnostics)) = result else {
+        panic!("expected a compile error, got {:?}", result);
+    };
+    let positions: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line_number, diagnostic.column))
+        .collect();
+    assert_eq!(vec![(1, 10), (2, 5), (3, 9)], positions);
+    assert_eq!(
+        "[line 2:5] error: Expect variable name.",
+        diagnostics[1].to_string()
+    );
+}
+
+#[test]
+fn test_escape_errors_point_at_the_escape() {
+    let mut vm = VirtualMachine::new();
+    let source = "let s = \"one\ntwo\n  x \\q y\";";
+    let result = vm.interpret(source);
+    let Err(InterpretError::CompileError(diagnostics)) = result else {
+        panic!("expected a compile error, got {:?}", result);
+    };
+    assert_eq!((3, 5), (diagnostics[0].line_number, diagnostics[0].column));
+    assert_eq!(
+        "error: Invalid escape sequence '\\q'.\n \
+         --> 3:5\n  \
+         |\n\
+         3 |   x \\q y\";\n  \
+         |     ^^\n",
+        diagnostics[0].render(source)
+    );
+}
+
+#[test]
+fn test_runtime_error_trace() {
+    let mut vm = VirtualMachine::new();
+    let source = "fn inner(x) {\n  return x + none;\n}\nfn outer() { return inner(1); }\nouter();";
+    let result = vm.interpret(source);
+    let Err(InterpretError::RuntimeError(error)) = result else {
+        panic!("expected a runtime error, got {:?}", result);
+    };
+    let frames: Vec<_> = error
+        .trace
+        .iter()
+        .map(|frame| (frame.function.as_str(), frame.line_number))
+        .collect();
+    assert_eq!(vec![("inner", 2), ("outer", 4), ("", 5)], frames);
+    assert_eq!(
+        "x + none",
+        &source[error.trace[0].span.start..error.trace[0].span.end]
+    );
+    assert!(error
+        .render()
+        .ends_with("[line 4] in outer()\n[line 5] in script\n"));
+}
+
+#[test]
+fn test_try_catch() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let caught = "";
+        fn risky() { throw Error("too big"); }
+        try { risky(); } catch (e) { caught = e.message + " " + String(e.trace[1]); }
+        try { print missing; } catch (e) { caught = caught + ", " + e.message; }
+        try { throw 42; } catch (e) { caught = caught + ", " + String(e); }
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(
+        Some(Value::from(
+            "too big [line 4] in script, Undefined variable 'missing', 42"
+        )),
+        vm.get_global("caught")
+    );
+}
+
+#[test]
+fn test_finally_runs_on_every_exit() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let order = "";
+        fn early() {
+            try { return 1; } finally { order = order + "return "; }
+        }
+        let result = early();
+        let i = 0;
+        while (i < 3) {
+            i = i + 1;
+            try {
+                if (i == 1) continue;
+                if (i == 3) break;
+            } catch (e) {}
+        }
+        let exits = "";
+        let j = 0;
+        while (j < 4) {
+            j = j + 1;
+            try {
+                try {
+                    if (j == 1) continue;
+                    if (j == 3) break;
+                } finally { exits = exits + "inner" + String(j) + " "; }
+            } finally { exits = exits + "outer" + String(j) + " "; }
+        }
+        try {
+            try { throw "inner"; } finally { order = order + "finally "; }
+        } catch (e) {
+            order = order + e;
+        }
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(1.0)), vm.get_global("result"));
+    assert_eq!(Some(Value::Number(3.0)), vm.get_global("i"));
+    assert_eq!(Some(Value::Number(3.0)), vm.get_global("j"));
+    assert_eq!(
+        Some(Value::from("inner1 outer1 inner2 outer2 inner3 outer3 ")),
+        vm.get_global("exits")
+    );
+    assert_eq!(
+        Some(Value::from("return finally inner")),
+        vm.get_global("order")
+    );
+}
+
+#[test]
+fn test_uncaught_throw() {
+    let mut vm = VirtualMachine::new();
+    let result = vm.interpret("fn f() { throw 7; }\nf();");
+    let Err(InterpretError::RuntimeError(error)) = result else {
+        panic!("expected a runtime error, got {:?}", result);
+    };
+    assert_eq!("Uncaught exception: 7", error.message);
+    assert_eq!(Some(Value::Number(7.0)), error.value);
+    assert_eq!(2, error.trace.len());
+    assert_eq!(
+        vec!["Expect 'catch' or 'finally' after try block."],
+        compile_messages(vm.interpret("try {} print 1;"))
+    );
+}
+
+#[test]
+fn test_for_in_ranges() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let sum = 0;
+        for x in 0..5 { sum = sum + x; }
+        let n = 3;
+        for x in 1..=n sum = sum + x * 100;
+        let skipped = "";
+        for x in 0..10 {
+            if (x == 1) continue;
+            if (x == 4) break;
+            skipped = skipped + String(x);
+        }
+        let range = 2..=4;
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(610.0)), vm.get_global("sum"));
+    assert_eq!(Some(Value::from("023")), vm.get_global("skipped"));
+    assert_eq!("2..=4", vm.get_global("range").unwrap().to_string());
+    assert_eq!(
+        "Range bounds must be numbers.",
+        runtime_message(vm.interpret("let r = 0..\"a\";"))
+    );
+}
+
+#[test]
+fn test_for_in_collections() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let out = "";
+        for item in ["a", "b"] { out = out + item; }
+        for (i, item) in ["c", "d"] { out = out + String(i) + item; }
+        let map = {"x": 1, "y": 2};
+        for key in map { out = out + key; }
+        for (key, value) in map { out = out + key + String(value); }
+        fn first_even(xs) {
+            for x in xs { if (x % 2 == 0) return x; }
+            return none;
+        }
+        let even = first_even([1, 3, 4, 5]);
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::from("ab0c1dxyx1y2")), vm.get_global("out"));
+    assert_eq!(Some(Value::Number(4.0)), vm.get_global("even"));
+    assert_eq!(
+        "Only lists and maps can be unpacked into two variables.",
+        runtime_message(vm.interpret("for (a, b) in 0..3 {}"))
+    );
+    assert_eq!(
+        "Only lists, maps, ranges and iterators can be iterated.",
+        runtime_message(vm.interpret("for x in 3 {}"))
+    );
+}
+
+#[test]
+fn test_iterator_protocol() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        struct Countdown {
+            fn new(n) { self.n = n; }
+            fn next() {
+                if (self.n == 0) return none;
+                self.n = self.n - 1;
+                return self.n + 1;
+            }
+        }
+        struct Bag {
+            fn new() { self.items = [7, 8]; }
+            fn iter() { return self.items; }
+        }
+        let out = "";
+        for c in Countdown(3) { out = out + String(c); }
+        for item in Bag() { out = out + String(item); }
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::from("32178")), vm.get_global("out"));
+    assert_eq!(
+        "Bag has no 'iter' or 'next' method.",
+        runtime_message(vm.interpret("struct Bag {}\nfor x in Bag() {}"))
+    );
+}
+
+#[test]
+fn test_compound_assignment() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        let g = 1;
+        g += 2; g *= 10; g -= 5; g /= 5; g %= 4; g ^= 3;
+        fn counter() {
+            let x = 2;
+            x ^= 2;
+            fn bump() { x += 1; }
+            bump();
+            return x;
+        }
+        let local = counter();
+        struct Total { fn new() { self.total = 0; } }
+        let totals = [Total()];
+        let calls = 0;
+        fn first() { calls += 1; return totals[0]; }
+        first().total += 5;
+        first().total *= 3;
+        let list = [1, 2, 3];
+        list[1] += 10;
+        let text = "a";
+        text += "b";
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(1.0)), vm.get_global("g"));
+    assert_eq!(Some(Value::Number(5.0)), vm.get_global("local"));
+    assert_eq!("15", vm.eval("totals[0].total;").unwrap().to_string());
+    assert_eq!(Some(Value::Number(2.0)), vm.get_global("calls"));
+    assert_eq!("[1, 12, 3]", vm.get_global("list").unwrap().to_string());
+    assert_eq!(Some(Value::from("ab")), vm.get_global("text"));
+    assert_eq!(
+        vec!["Invalid assignment target."],
+        compile_messages(vm.interpret("g + 1 += 2;"))
+    );
+}
+
+#[test]
+fn test_anonymous_functions() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        fn apply(f, x) { return f(x); }
+        let doubled = apply(fn(x) { return x * 2; }, 4);
+        let squared = apply(|x| x * x, 5);
+        let answer = || 42;
+        fn adder(n) { return |x| x + n; }
+        let sum = adder(10)(5);
+        let called = false;
+        fn() { called = true; }();
+        struct Counter {
+            fn new() { self.n = 0; }
+            fn incrementer() { return || self.n += 1; }
+        }
+        let counter = Counter();
+        let increment = counter.incrementer();
+        increment();
+        increment();
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(8.0)), vm.get_global("doubled"));
+    assert_eq!(Some(Value::Number(25.0)), vm.get_global("squared"));
+    assert_eq!(
+        "<fn anonymous@5>",
+        vm.get_global("answer").unwrap().to_string()
+    );
+    assert_eq!(Some(Value::Number(15.0)), vm.get_global("sum"));
+    assert_eq!(Some(Value::Bool(true)), vm.get_global("called"));
+    assert_eq!(Ok(Value::Number(2.0)), vm.eval("counter.n;"));
+
+    let result = vm.interpret("let bad = fn() {\n  return none + 1;\n};\nbad();");
+    let Err(InterpretError::RuntimeError(error)) = result else {
+        panic!("expected a runtime error, got {:?}", result);
+    };
+    assert_eq!("[line 2] in <fn anonymous@1>", error.trace[0].to_string());
+}
+
+#[test]
+fn test_match_literals_and_guards() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        fn describe(value) {
+            return match value {
+                1 | 2 => "small",
+                "x" => "x",
+                -1 => "minus one",
+                none => "nothing",
+                n if n > 10 => "big " + String(n),
+                _ => "other",
+            };
+        }
+        let described = describe(2) + ", " + describe("x") + ", " + describe(-1) + ", "
+            + describe(none) + ", " + describe(50) + ", " + describe(5);
+        let total = 0;
+        for i in 0..4 {
+            match i % 2 {
+                0 => { total += 10; }
+                _ => { total += 1; }
+            }
+        }
+        let sum = 1 + match total { 22 => 2, _ => 0 };
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(
+        Some(Value::from("small, x, minus one, nothing, big 50, other")),
+        vm.get_global("described")
+    );
+    assert_eq!(Some(Value::Number(22.0)), vm.get_global("total"));
+    assert_eq!(Some(Value::Number(3.0)), vm.get_global("sum"));
+    assert_eq!(
+        "No match arm matches 3.",
+        runtime_message(vm.interpret("match 3 { 1 => 1 }"))
+    );
+}
+
+#[test]
+fn test_match_destructuring() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        struct Point { fn new(x, y) { self.x = x; self.y = y; } }
+        struct Point3: Point { fn new(x, y, z) { super.new(x, y); self.z = z; } }
+        fn locate(value) {
+            return match value {
+                Point { x: 0, y: 0 } => "origin",
+                Point { x: 0, y } => "y " + String(y),
+                Point { x, y } if x == y => "diagonal",
+                Point { x, y } => String(x) + "," + String(y),
+                [] => "empty",
+                [first] => "one " + String(first),
+                [1, second, ..] => "then " + String(second),
+                _ => "other",
+            };
+        }
+        let located = locate(Point(0, 0)) + "; " + locate(Point(0, 3)) + "; "
+            + locate(Point(2, 2)) + "; " + locate(Point3(4, 5, 6)) + "; " + locate([]) + "; "
+            + locate([9]) + "; " + locate([1, 7, 8]) + "; " + locate([2, 7]);
+        let add = match [3, 4] { [a, b] => || a + b };
+        let added = add();
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(
+        Some(Value::from(
+            "origin; y 3; diagonal; 4,5; empty; one 9; then 7; other"
+        )),
+        vm.get_global("located")
+    );
+    assert_eq!(Some(Value::Number(7.0)), vm.get_global("added"));
+    assert_eq!(
+        vec!["Can't bind variables in '|' alternatives."],
+        compile_messages(vm.interpret("match 1 { 1 | n => n }"))
+    );
+
+    let source = r#"
+        fn first_even(xs) {
+            for x in xs {
+                match x % 2 { 0 => { return x; }, _ => {} }
+            }
+            return none;
+        }
+        fn pair(a, b) { return String(a) + String(b); }
+        fn nested(n) {
+            let label = "n" + match n { 0 => "zero", m if m < 0 => "neg", m => pair(m, match m { 1 => "!", _ => "" }) };
+            return label;
+        }
+        let seen = "";
+        let i = 0;
+        while (i < 10) {
+            i = i + 1;
+            let kept = 100 + match i { 2 => { continue; }, 5 => { break; }, k => k };
+            seen = seen + String(kept) + " ";
+        }
+        let found = first_even([1, 3, 6, 8]);
+        let labels = nested(0) + " " + nested(-2) + " " + nested(1) + " " + pair(2, match 3 { t => t * 2 });
+        let depth = 0;
+        fn recurse(n) { return match n { 0 => 0, _ => 1 + recurse(n - 1) }; }
+        depth = recurse(200);
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::from("101 103 104 ")), vm.get_global("seen"));
+    assert_eq!(Some(Value::Number(6.0)), vm.get_global("found"));
+    assert_eq!(
+        Some(Value::from("nzero nneg n1! 26")),
+        vm.get_global("labels")
+    );
+    assert_eq!(Some(Value::Number(200.0)), vm.get_global("depth"));
+
+    let locals: String = (0..250).map(|i| format!("let v{} = 0; ", i)).collect();
+    let source = format!(
+        "fn crowded() {{ {} let l = [1, 2, 3, 4, 5, 6, 7, 8, match 0 {{ _ => 1 }}]; }}",
+        locals
+    );
+    assert_eq!(
+        vec!["Too many local variables in function."],
+        compile_messages(vm.interpret(&source))
+    );
+}
+
+#[test]
+fn test_enums() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        enum Shape { Circle(r), Rect(w, h), Empty }
+        let circle = Shape.Circle(2);
+        let rect = Shape.Rect;
+        let shown = String(circle) + "; " + String(rect(1, 2)) + "; " + String(Shape.Empty);
+        let radius = circle.r;
+        let equal = circle == Shape.Circle(2) and Shape.Empty == Shape.Empty;
+        let unequal = circle == Shape.Circle(3) or circle == Shape.Empty;
+        fn area(shape) {
+            return match shape {
+                Shape.Circle(r) => 3 * r * r,
+                Shape.Rect(w, h) if w == h => "square",
+                Shape.Rect(w, h) => w * h,
+                Shape.Empty => 0,
+            };
+        }
+        let areas = String(area(circle)) + "; " + String(area(rect(2, 2))) + "; "
+            + String(area(rect(2, 3))) + "; " + String(area(Shape.Empty));
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(
+        Some(Value::from(
+            "Shape.Circle(2); Shape.Rect(1, 2); Shape.Empty"
+        )),
+        vm.get_global("shown")
+    );
+    assert_eq!(Some(Value::Number(2.0)), vm.get_global("radius"));
+    assert_eq!(Some(Value::Bool(true)), vm.get_global("equal"));
+    assert_eq!(Some(Value::Bool(false)), vm.get_global("unequal"));
+    assert_eq!(
+        Some(Value::from("12; square; 6; 0")),
+        vm.get_global("areas")
+    );
+    assert_eq!(
+        "Shape has no variant 'Square'.",
+        runtime_message(vm.interpret("Shape.Square;"))
+    );
+    assert_eq!(
+        "Shape.Circle has 1 fields but the pattern has 2.",
+        runtime_message(vm.interpret("match circle { Shape.Circle(a, b) => a, _ => 0 }"))
+    );
+    assert_eq!(
+        vec!["Already a variant with this name in this enum."],
+        compile_messages(vm.interpret("enum Twice { A, A }"))
+    );
+}
+
+#[test]
+fn test_declared_fields() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        struct Point {
+            x, y = 0;
+            fn sum() { return self.x + self.y; }
+        }
+        struct Point3: Point { z = 3; }
+        let x = 5;
+        let given = Point { x: 1, y: 2 }.sum();
+        let shorthand = Point { x }.sum();
+        let called = Point().y;
+        let inherited = Point3 { x: 1 }.sum() + Point3 { x: 1 }.z;
+        let total = 0;
+        for point in [Point { x: 1 }, Point { x: 2, y: 3 }] { total += point.sum(); }
+        let matched = match (Point { x: 1, y: 4 }) { Point { x: 1, y } => y, _ => 0 };
+        let made = 0;
+        fn make() { made += 1; return made; }
+        struct Cache { seen = {}, id = make(); }
+        let first = Cache();
+        let second = Cache { id: 0 };
+        first.seen["a"] = 1;
+        let shared = keys(second.seen);
+        let ids = [first.id, second.id, Cache {}.id, made];
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(3.0)), vm.get_global("given"));
+    assert_eq!(Some(Value::Number(5.0)), vm.get_global("shorthand"));
+    assert_eq!(Some(Value::Number(0.0)), vm.get_global("called"));
+    assert_eq!(Some(Value::Number(4.0)), vm.get_global("inherited"));
+    assert_eq!(Some(Value::Number(6.0)), vm.get_global("total"));
+    assert_eq!(Some(Value::Number(4.0)), vm.get_global("matched"));
+    assert_eq!(Ok(Value::Bool(true)), vm.eval("shared == [];"));
+    assert_eq!(Ok(Value::Bool(true)), vm.eval("ids == [1, 0, 2, 2];"));
+    assert_eq!(
+        "Point has no field 'z'.",
+        runtime_message(vm.interpret("let p = Point { x: 1 }; p.z = 2;"))
+    );
+    assert_eq!(
+        "Missing field 'x' in Point literal.",
+        runtime_message(vm.interpret("Point { y: 1 };"))
+    );
+    assert_eq!(
+        vec!["Already a field with this name in this struct."],
+        compile_messages(vm.interpret("struct Twice { a, a; }"))
+    );
+    assert_eq!(
+        vec!["Already a field with this name in this literal."],
+        compile_messages(vm.interpret("Point { x: 1, x: 2 };"))
+    );
+}
+
+#[test]
+fn test_field_calls_can_write_their_instance() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        struct Holder {}
+        let holder = Holder();
+        holder.count = 0;
+        holder.bump = fn() { holder.count += 1; };
+        holder.bump();
+        struct Marker { mark = holder.marked = true; }
+        holder.make = Marker;
+        holder.make();
+        let count = holder.count;
+        let marked = holder.marked;
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(1.0)), vm.get_global("count"));
+    assert_eq!(Some(Value::Bool(true)), vm.get_global("marked"));
+}
+
+#[test]
+fn test_static_functions() {
+    let mut vm = VirtualMachine::new();
+    let source = r#"
+        struct Point {
+            x, y = 0;
+            static fn origin() { return Point { x: 0 }; }
+            static fn of(x, y) { return Point { x, y }; }
+            fn sum() { return self.x + self.y; }
+        }
+        struct Point3: Point { static fn unit() { return Point::of(1, 1); } }
+        enum Shape { Rect(w, h), Empty }
+        fn area(shape) {
+            return match shape { Shape::Rect(w, h) => w * h, Shape::Empty => 0 };
+        }
+        let areas = [area(Shape::Rect(2, 3)), area(Shape::Empty)];
+        let origin = Point::origin().sum();
+        let dotted = Point.of(2, 3).sum();
+        let of = Point::of;
+        let stored = of(4, 5).sum();
+        let inherited = Point3::unit().sum() + Point3.origin().x;
+    "#;
+    assert_eq!(Ok(()), vm.interpret(source));
+    assert_eq!(Some(Value::Number(0.0)), vm.get_global("origin"));
+    assert_eq!(Some(Value::Number(5.0)), vm.get_global("dotted"));
+    assert_eq!(Some(Value::Number(9.0)), vm.get_global("stored"));
+    assert_eq!(Some(Value::Number(2.0)), vm.get_global("inherited"));
+    assert_eq!(Ok(Value::Bool(true)), vm.eval("areas == [6, 0];"));
+    assert_eq!(
+        "Point has no static function 'sum'.",
+        runtime_message(vm.interpret("Point::sum();"))
+    );
+    assert_eq!(
+        vec!["Can't use 'self' in a static function."],
+        compile_messages(vm.interpret("struct Bad { static fn f() { return self; } }"))
+    );
+}